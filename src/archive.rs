@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use tar::{Archive, EntryType};
+
+use crate::compare::{compare, Comparison, Scan, ScanEntry};
+use crate::core::{Node, Tree};
+use crate::export::entry_kind;
+use crate::mtree::sha256;
+
+/// gzip 文件开头的两个字节
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Compare the listed directory against the contents of a tar archive, gzip-compressed or
+/// not, without extracting it. Entries only on disk count as added and entries only in the
+/// archive as removed. Files must keep their type and size, and with `digests` also their
+/// SHA-256; modification times are ignored since archiving tools do not all keep them.
+///
+/// When everything in the archive sits below one top-level directory, as with
+/// `tar czf backup.tar.gz project/`, that directory stands for the listed one.
+pub fn compare_archive(tree: &Tree, archive: &Path, digests: bool) -> io::Result<Comparison> {
+    let local = scan_tree(tree, digests);
+    let mut entries = read_archive(archive, digests)?;
+    if let Some(top) = single_top_dir(&entries) {
+        let name = tree.root.item.path();
+        let name = name.file_name();
+        if name == Some(top.as_os_str()) || !local.entries().any(|(path, _)| path == top) {
+            entries = strip_top_dir(entries, &top);
+        }
+    }
+    Ok(compare(&Scan::new(archive.to_path_buf(), entries), &local))
+}
+
+/// 目录树中的条目，按相对于根目录的路径
+fn scan_tree(tree: &Tree, digests: bool) -> Scan {
+    let root = tree.root.item.path();
+    let mut entries = BTreeMap::new();
+    for child in &tree.root.children {
+        insert_node(child, &root, digests, &mut entries);
+    }
+    Scan::new(root, entries)
+}
+
+fn insert_node(
+    node: &Node,
+    root: &Path,
+    digests: bool,
+    entries: &mut BTreeMap<PathBuf, ScanEntry>,
+) {
+    let item = &node.item;
+    let path = item.path();
+    let kind = entry_kind(item);
+    // 归档中链接的大小总是 0，和磁盘上链接本身的大小无关
+    let size = match kind {
+        "file" => item.metadata.as_ref().map_or(0, |metadata| metadata.len()),
+        _ => 0,
+    };
+    let digest = (digests && kind == "file")
+        .then(|| File::open(&path).and_then(sha256).ok())
+        .flatten();
+    let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+    entries.insert(
+        relative,
+        ScanEntry {
+            kind: kind.to_string(),
+            size,
+            modified: None,
+            digest,
+        },
+    );
+    for child in &node.children {
+        insert_node(child, root, digests, entries);
+    }
+}
+
+/// 按内容而不是扩展名判断是否经过 gzip 压缩
+fn read_archive(path: &Path, digests: bool) -> io::Result<BTreeMap<PathBuf, ScanEntry>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut magic = [0; 2];
+    let compressed = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+    file.rewind()?;
+    if compressed {
+        read_tar(GzDecoder::new(file), digests)
+    } else {
+        read_tar(file, digests)
+    }
+}
+
+fn read_tar(reader: impl Read, digests: bool) -> io::Result<BTreeMap<PathBuf, ScanEntry>> {
+    let mut entries = BTreeMap::new();
+    for entry in Archive::new(reader).entries()? {
+        let mut entry = entry?;
+        let path = normalize(&entry.path()?);
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+        let kind = match entry.header().entry_type() {
+            EntryType::Directory => "directory",
+            EntryType::Symlink => "link",
+            EntryType::Link => {
+                // 硬链接在归档中只记录目标，内容和大小与目标相同
+                let target = entry.link_name()?.map(|target| normalize(&target));
+                if let Some(linked) = target.and_then(|target| entries.get(&target).cloned()) {
+                    entries.insert(path, linked);
+                }
+                continue;
+            }
+            EntryType::XGlobalHeader => continue,
+            // 与导出时的类型名一致
+            EntryType::Fifo => "fifo",
+            EntryType::Char => "char",
+            EntryType::Block => "block",
+            _ => "file",
+        };
+        let size = if kind == "file" { entry.size() } else { 0 };
+        let digest = if digests && kind == "file" {
+            Some(sha256(&mut entry)?)
+        } else {
+            None
+        };
+        entries.insert(
+            path,
+            ScanEntry {
+                kind: kind.to_string(),
+                size,
+                modified: None,
+                digest,
+            },
+        );
+    }
+    // 有的归档不为上级目录单独记录条目
+    let parents: Vec<PathBuf> = entries
+        .keys()
+        .flat_map(|path| path.ancestors().skip(1))
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .collect();
+    for parent in parents {
+        entries.entry(parent).or_insert_with(|| ScanEntry {
+            kind: "directory".to_string(),
+            size: 0,
+            modified: None,
+            digest: None,
+        });
+    }
+    Ok(entries)
+}
+
+/// 去掉 `./` 之类的前缀，和目录树中的相对路径一致
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+/// 所有条目都在同一个顶层目录下时返回这个目录
+fn single_top_dir(entries: &BTreeMap<PathBuf, ScanEntry>) -> Option<PathBuf> {
+    let (first, entry) = entries.iter().next()?;
+    let top = PathBuf::from(first.components().next()?.as_os_str());
+    let shared = entry.kind == "directory"
+        && &top == first
+        && entries.keys().all(|path| path.starts_with(&top));
+    shared.then_some(top)
+}
+
+fn strip_top_dir(
+    entries: BTreeMap<PathBuf, ScanEntry>,
+    top: &Path,
+) -> BTreeMap<PathBuf, ScanEntry> {
+    entries
+        .into_iter()
+        .filter_map(|(path, entry)| {
+            let relative = path.strip_prefix(top).ok()?;
+            (!relative.as_os_str().is_empty()).then(|| (relative.to_path_buf(), entry))
+        })
+        .collect()
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+
+use crate::core::DirSummary;
+
+/// `--assert-*` 参数设置的上限
+#[derive(Debug, Default, Clone)]
+pub struct Thresholds {
+    pub max_files: Option<usize>,
+    pub max_dirs: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub max_size: Option<u64>,
+    /// `--fail-if-empty`：没有列出任何文件时失败
+    pub fail_if_empty: bool,
+}
+
+/// 超出上限的一项检查
+#[derive(Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub name: &'static str,
+    pub limit: u64,
+    pub actual: u64,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "assertion-failed check={} limit={} actual={}",
+            self.name, self.limit, self.actual
+        )
+    }
+}
+
+impl Thresholds {
+    pub fn is_empty(&self) -> bool {
+        self.max_files.is_none()
+            && self.max_dirs.is_none()
+            && self.max_depth.is_none()
+            && self.max_size.is_none()
+            && !self.fail_if_empty
+    }
+
+    /// Whether `--fail-if-empty` is set and no files or symlinks were listed; directories
+    /// alone do not count.
+    pub fn is_unmet(&self, summary: &DirSummary) -> bool {
+        self.fail_if_empty && summary.num_files + summary.num_symlinks == 0
+    }
+
+    /// Compare the scanned tree against every configured threshold.
+    pub fn check(&self, summary: &DirSummary) -> Vec<Violation> {
+        let checks = [
+            (
+                "max-files",
+                self.max_files.map(|v| v as u64),
+                summary.num_files as u64,
+            ),
+            (
+                "max-dirs",
+                self.max_dirs.map(|v| v as u64),
+                summary.num_folders as u64,
+            ),
+            (
+                "max-depth",
+                self.max_depth.map(|v| v as u64),
+                summary.max_depth as u64,
+            ),
+            ("max-size", self.max_size, summary.total_size),
+        ];
+        checks
+            .into_iter()
+            .filter_map(|(name, limit, actual)| {
+                let limit = limit?;
+                (actual > limit).then_some(Violation {
+                    name,
+                    limit,
+                    actual,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,19 @@
+use std::path::Path;
+
+/// 识别项目类型的标记文件和对应的徽标，按显示顺序排列
+pub const PROJECT_MARKERS: &[(&str, &str)] = &[
+    ("Cargo.toml", "rust"),
+    ("package.json", "node"),
+    ("pyproject.toml", "python"),
+    ("go.mod", "go"),
+];
+
+/// Return the badges of the project markers found directly in `dir`, e.g. `["rust", "node"]`
+/// for a crate that also has a `package.json`.
+pub fn project_badges(dir: &Path) -> Vec<&'static str> {
+    PROJECT_MARKERS
+        .iter()
+        .filter(|(marker, _)| dir.join(marker).is_file())
+        .map(|(_, badge)| *badge)
+        .collect()
+}
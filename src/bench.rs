@@ -0,0 +1,120 @@
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::core::build_tree;
+use crate::file_iterator::ScanTimings;
+use crate::render::{render, Renderer};
+use crate::terminal::Terminal;
+use crate::Config;
+
+/// Time spent in each phase of one listing.
+#[derive(Debug, Clone, Copy)]
+pub struct BenchReport {
+    /// 列出的目录和文件数，不含根目录
+    pub entries: usize,
+    pub scan: ScanTimings,
+    /// 组装、排序和统计整棵树的时间（扣除遍历各阶段之后的部分）
+    pub build: Duration,
+    pub render: Duration,
+    pub total: Duration,
+}
+
+impl BenchReport {
+    pub fn entries_per_second(&self) -> f64 {
+        let seconds = self.total.as_secs_f64();
+        if seconds > 0.0 {
+            self.entries as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// List `path` with `config` once, rendering into a sink, and time each phase.
+pub fn bench(path: &Path, config: &Config) -> io::Result<BenchReport> {
+    let started = Instant::now();
+    let tree = build_tree(path, config);
+    let built = Instant::now();
+    let mut sink = Terminal::sink();
+    let mut renderer = Renderer::new(&config.display, &mut sink);
+    render(&tree, &mut renderer)?;
+    let render_time = built.elapsed();
+
+    let scan = tree.timings;
+    let scanned = scan.read_dir + scan.stat + scan.filter;
+    Ok(BenchReport {
+        entries: tree.summary.num_folders + tree.summary.num_files + tree.summary.num_symlinks,
+        scan,
+        build: (built - started).saturating_sub(scanned),
+        render: render_time,
+        total: started.elapsed(),
+    })
+}
+
+/// Read and write counters the OS keeps for this process, where it exposes them (Linux
+/// `/proc/self/io`).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IoCounters {
+    pub read_syscalls: u64,
+    pub write_syscalls: u64,
+    /// 真正从存储设备读取的字节数，命中页缓存的部分不算
+    pub read_bytes: u64,
+}
+
+impl IoCounters {
+    #[cfg(target_os = "linux")]
+    pub fn current() -> Option<IoCounters> {
+        let text = std::fs::read_to_string("/proc/self/io").ok()?;
+        let mut counters = IoCounters::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim().parse().ok()?;
+            match key {
+                "syscr" => counters.read_syscalls = value,
+                "syscw" => counters.write_syscalls = value,
+                "read_bytes" => counters.read_bytes = value,
+                _ => {}
+            }
+        }
+        Some(counters)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn current() -> Option<IoCounters> {
+        None
+    }
+
+    /// 从 `earlier` 到现在的增量
+    pub fn since(&self, earlier: &IoCounters) -> IoCounters {
+        IoCounters {
+            read_syscalls: self.read_syscalls.saturating_sub(earlier.read_syscalls),
+            write_syscalls: self.write_syscalls.saturating_sub(earlier.write_syscalls),
+            read_bytes: self.read_bytes.saturating_sub(earlier.read_bytes),
+        }
+    }
+}
+
+/// What `--timing` prints after the report.
+#[derive(Debug, Clone, Copy)]
+pub struct TimingReport {
+    /// 从开始遍历到输出完成的时间
+    pub elapsed: Duration,
+    /// 列出的条目数，不含根目录
+    pub entries: usize,
+    pub scan: ScanTimings,
+    pub io: Option<IoCounters>,
+}
+
+impl TimingReport {
+    pub fn entries_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.entries as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// 按顺序尝试的剪贴板命令
+#[cfg(target_os = "macos")]
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[&["pbcopy"]];
+
+#[cfg(target_os = "windows")]
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[&["clip"]];
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// Place `text` on the system clipboard using the first available platform utility.
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    for command in CLIPBOARD_COMMANDS {
+        let child = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+        // 写入失败时也先关闭 stdin 并等子进程退出，不留下僵尸进程
+        let written = match child.stdin.take() {
+            Some(mut stdin) => stdin.write_all(text.as_bytes()),
+            None => Ok(()),
+        };
+        let status = child.wait()?;
+        written?;
+        if status.success() {
+            return Ok(());
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no clipboard utility found",
+    ))
+}
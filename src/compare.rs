@@ -0,0 +1,283 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::export::{read_rows, JsonDocument, JsonNode};
+use crate::symbol::{set_line_prefix, Charset};
+
+/// 扫描结果中记录的一个条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanEntry {
+    /// `directory`、`file`、`link` 或 `error`
+    pub kind: String,
+    pub size: u64,
+    /// 导出时没有 `--timefmt` 则是距纪元的秒数
+    pub modified: Option<String>,
+    /// 文件内容的 SHA-256，只有 `diff --hash` 时才计算
+    pub digest: Option<String>,
+}
+
+/// A scan saved earlier with `--output-format json` or `ndjson`, or with `--sqlite`. Paths are
+/// relative to the root of the scan, so scans of the same tree taken on different machines or
+/// under different mount points compare equal.
+pub struct Scan {
+    root: PathBuf,
+    /// 根目录自身的信息
+    root_entry: Option<ScanEntry>,
+    entries: BTreeMap<PathBuf, ScanEntry>,
+}
+
+/// SQLite 数据库文件开头的 16 个字节
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+impl Scan {
+    /// Read a scan, telling the formats apart by content rather than by file name.
+    pub fn load(path: &Path) -> io::Result<Scan> {
+        let bytes = fs::read(path)?;
+        if bytes.starts_with(SQLITE_HEADER) {
+            return Scan::from_sqlite(path).map_err(io::Error::other);
+        }
+        let text = String::from_utf8(bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        match serde_json::from_str::<JsonDocument>(&text) {
+            Ok(document) => Ok(Scan::from_json(document)),
+            Err(_) => Scan::from_ndjson(&text),
+        }
+    }
+
+    /// A scan of entries collected some other way, such as from the contents of an archive,
+    /// keyed by path relative to `root`.
+    pub fn new(root: PathBuf, entries: BTreeMap<PathBuf, ScanEntry>) -> Scan {
+        Scan {
+            root,
+            root_entry: None,
+            entries,
+        }
+    }
+
+    /// 扫描时的根目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn root_entry(&self) -> Option<&ScanEntry> {
+        self.root_entry.as_ref()
+    }
+
+    /// Entries by path relative to the root, in pre-order: every directory comes right before
+    /// the entries below it.
+    pub fn entries(&self) -> impl Iterator<Item = (&Path, &ScanEntry)> {
+        self.entries
+            .iter()
+            .map(|(path, entry)| (path.as_path(), entry))
+    }
+
+    fn from_json(document: JsonDocument) -> Scan {
+        let tree = document.tree;
+        let mut scan = Scan {
+            root: PathBuf::from(&tree.path),
+            root_entry: Some(ScanEntry {
+                kind: tree.kind,
+                size: tree.size,
+                modified: tree.modified,
+                digest: None,
+            }),
+            entries: BTreeMap::new(),
+        };
+        for child in tree.children.into_iter().flatten() {
+            scan.insert_json(child);
+        }
+        scan
+    }
+
+    fn insert_json(&mut self, node: JsonNode) {
+        for child in node.children.into_iter().flatten() {
+            self.insert_json(child);
+        }
+        let entry = ScanEntry {
+            kind: node.kind,
+            size: node.size,
+            modified: node.modified,
+            digest: None,
+        };
+        self.insert(&node.path, entry);
+    }
+
+    fn from_ndjson(text: &str) -> io::Result<Scan> {
+        let rows = read_rows(text)?;
+        let root = rows.iter().find(|row| row.depth == 0);
+        let mut scan = Scan {
+            root: root.map(|row| PathBuf::from(&row.path)).unwrap_or_default(),
+            root_entry: root.map(|row| ScanEntry {
+                kind: row.kind.clone(),
+                size: row.size,
+                modified: row.modified.clone(),
+                digest: None,
+            }),
+            entries: BTreeMap::new(),
+        };
+        for row in rows.into_iter().filter(|row| row.depth > 0) {
+            let entry = ScanEntry {
+                kind: row.kind,
+                size: row.size,
+                modified: row.modified,
+                digest: None,
+            };
+            scan.insert(&row.path, entry);
+        }
+        Ok(scan)
+    }
+
+    fn from_sqlite(path: &Path) -> rusqlite::Result<Scan> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut statement =
+            connection.prepare("SELECT path, type, size, mtime, depth FROM entries")?;
+        let mut scan = Scan {
+            root: PathBuf::new(),
+            root_entry: None,
+            entries: BTreeMap::new(),
+        };
+        let mut rows = statement.query([])?;
+        let mut entries = Vec::new();
+        while let Some(row) = rows.next()? {
+            let path: String = row.get(0)?;
+            let entry = ScanEntry {
+                kind: row.get(1)?,
+                size: row.get::<_, i64>(2)? as u64,
+                modified: row.get::<_, Option<i64>>(3)?.map(|mtime| mtime.to_string()),
+                digest: None,
+            };
+            if row.get::<_, i64>(4)? == 0 {
+                scan.root = PathBuf::from(path);
+                scan.root_entry = Some(entry);
+                continue;
+            }
+            entries.push((path, entry));
+        }
+        for (path, entry) in entries {
+            scan.insert(&path, entry);
+        }
+        Ok(scan)
+    }
+
+    fn insert(&mut self, path: &str, entry: ScanEntry) {
+        let path = Path::new(path);
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.entries.insert(relative.to_path_buf(), entry);
+    }
+}
+
+/// 一个条目在两次扫描之间的变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    /// 类型、大小、修改时间或内容摘要不同
+    Modified,
+}
+
+#[derive(Debug)]
+pub struct Comparison {
+    /// 新扫描的根目录，作为变化树的根
+    pub root: PathBuf,
+    changes: BTreeMap<PathBuf, (Change, Option<(u64, u64)>)>,
+}
+
+impl Comparison {
+    /// 按变化类型统计的条目数：新增、删除、修改
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let count = |change| self.changes.values().filter(|(c, _)| *c == change).count();
+        (
+            count(Change::Added),
+            count(Change::Removed),
+            count(Change::Modified),
+        )
+    }
+}
+
+/// Compare two scans by relative path. Entries count as modified when their type, size,
+/// modification time or, if both scans have one, content digest differs; for directories the
+/// size is ignored since it depends on the filesystem.
+pub fn compare(old: &Scan, new: &Scan) -> Comparison {
+    let mut changes = BTreeMap::new();
+    for (path, before) in &old.entries {
+        match new.entries.get(path) {
+            None => {
+                changes.insert(path.clone(), (Change::Removed, None));
+            }
+            Some(after) if is_modified(before, after) => {
+                let sizes = (before.size != after.size).then_some((before.size, after.size));
+                changes.insert(path.clone(), (Change::Modified, sizes));
+            }
+            Some(_) => {}
+        }
+    }
+    for path in new.entries.keys() {
+        if !old.entries.contains_key(path) {
+            changes.insert(path.clone(), (Change::Added, None));
+        }
+    }
+    Comparison {
+        root: new.root.clone(),
+        changes,
+    }
+}
+
+fn is_modified(before: &ScanEntry, after: &ScanEntry) -> bool {
+    before.kind != after.kind
+        || before.modified != after.modified
+        || (before.kind != "directory" && before.size != after.size)
+        || matches!((&before.digest, &after.digest), (Some(a), Some(b)) if a != b)
+}
+
+/// Render the changed entries as a tree with their unchanged parent directories, marking each
+/// entry with `+` (added), `-` (removed) or `~` (modified).
+pub fn render_changes(comparison: &Comparison, charset: Charset, indent: usize) -> String {
+    // 变化的条目加上它们的上级目录，按路径排序后就是先序遍历的顺序
+    let mut paths: BTreeSet<&Path> = BTreeSet::new();
+    for path in comparison.changes.keys() {
+        for ancestor in path.ancestors() {
+            if ancestor.as_os_str().is_empty() || !paths.insert(ancestor) {
+                break;
+            }
+        }
+    }
+    let paths: Vec<&Path> = paths.into_iter().collect();
+    let depths: Vec<usize> = paths.iter().map(|path| path.components().count()).collect();
+    // 从后往前找出每个条目在同一父目录下后面是否还有条目
+    let mut has_sibling = vec![false; paths.len()];
+    let mut seen_at_depth: Vec<bool> = Vec::new();
+    for (index, &depth) in depths.iter().enumerate().rev() {
+        seen_at_depth.resize(depth + 1, false);
+        has_sibling[index] = seen_at_depth[depth];
+        seen_at_depth[depth] = true;
+    }
+
+    let mut output = format!("{}\n", comparison.root.display());
+    let mut symbol_switch_list = Vec::new();
+    let mut prefix = String::new();
+    for (index, path) in paths.iter().enumerate() {
+        symbol_switch_list.truncate(depths[index] - 1);
+        symbol_switch_list.push(has_sibling[index]);
+        set_line_prefix(&symbol_switch_list, &mut prefix, charset, indent, None);
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let (mark, sizes) = match comparison.changes.get(*path) {
+            Some((Change::Added, sizes)) => ("+ ", sizes),
+            Some((Change::Removed, sizes)) => ("- ", sizes),
+            Some((Change::Modified, sizes)) => ("~ ", sizes),
+            None => ("", &None),
+        };
+        output.push_str(&prefix);
+        output.push_str(mark);
+        output.push_str(&name);
+        if let Some((before, after)) = sizes {
+            output.push_str(&format!("  ({} -> {})", before, after));
+        }
+        output.push('\n');
+    }
+    output
+}
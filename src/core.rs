@@ -1,79 +1,573 @@
-use std::io;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::{OsStr, OsString};
 use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::file_iterator::{FileItem, FileIterator};
+use clap::ValueEnum;
+
+use crate::datetime::TimeKind;
+use crate::file_iterator::{
+    file_id, has_hard_links, is_empty_dir, FileItem, FileIterator, ScanTimings,
+};
 use crate::filter::FilteredIterator;
-use crate::symbol::{print_path, set_line_prefix};
-use crate::Config;
+use crate::size::allocated_size;
+use crate::sort::{NameOrder, SortKey};
+use crate::symbol::{is_executable, special_kind, SpecialKind};
+use crate::{Config, FilterOptions, TraversalOptions};
+
+/// 扫描完成后完整保存在内存中的目录树
+pub struct Tree {
+    pub root: Node,
+    pub summary: DirSummary,
+    /// 遍历各阶段的耗时，`bench` 子命令和 `--timing` 使用
+    pub timings: ScanTimings,
+}
 
-pub struct DirTree<'a> {
-    term: &'a mut Box<term::StdoutTerminal>,
-    config: Config,
+/// 条目在扁平的机器可读输出中的顺序，`--traversal`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Traversal {
+    /// Depth-first: every directory is followed by its entries, as in the tree
+    #[default]
+    Dfs,
+    /// Breadth-first: all entries of one level come before those of the next
+    Bfs,
 }
 
-impl<'a> DirTree<'a> {
-    pub fn new(config: Config, term: &'a mut Box<term::StdoutTerminal>) -> DirTree<'a> {
-        DirTree { config, term }
+impl Tree {
+    /// Every node of the tree, starting with the root, in the order of `traversal`. Siblings
+    /// keep their listing order either way.
+    pub fn nodes(&self, traversal: Traversal) -> Vec<&Node> {
+        let mut nodes = Vec::new();
+        match traversal {
+            Traversal::Dfs => {
+                let mut stack = vec![&self.root];
+                while let Some(node) = stack.pop() {
+                    nodes.push(node);
+                    stack.extend(node.children.iter().rev());
+                }
+            }
+            Traversal::Bfs => {
+                nodes.push(&self.root);
+                let mut index = 0;
+                while let Some(node) = nodes.get(index) {
+                    nodes.extend(node.children.iter());
+                    index += 1;
+                }
+            }
+        }
+        nodes
     }
-    pub fn print_folders(&mut self, path: &Path) -> io::Result<DirSummary> {
-        let mut summary = DirSummary::init();
 
-        let mut symbol_switch_list: Vec<bool> = Vec::new();
-        let mut prefix = String::new();
+    /// Roughly how many bytes the entries of the tree take in memory.
+    pub fn footprint(&self) -> usize {
+        fn walk(node: &Node) -> usize {
+            footprint(&node.item) + node.children.iter().map(walk).sum::<usize>()
+        }
+        walk(&self.root)
+    }
+}
 
-        for entry in self.get_iterator(path) {
-            self.cal_symbol_switch(&mut symbol_switch_list, entry.level, entry.is_last);
+pub struct Node {
+    pub item: FileItem,
+    pub children: Vec<Node>,
+    /// 文件为自身大小，目录为其下所有列出文件的大小之和
+    pub size: u64,
+    /// `--inode-usage` 时条目及其下所有条目占用的 inode 数
+    pub inodes: u64,
+}
 
-            if entry.is_dir() {
-                summary.num_folders += 1;
-            } else {
-                summary.num_files += 1;
-            }
+impl Node {
+    pub fn new(item: FileItem) -> Node {
+        Node {
+            item,
+            children: Vec::new(),
+            size: 0,
+            inodes: 0,
+        }
+    }
+}
 
-            set_line_prefix(&symbol_switch_list, &mut prefix);
-            self.print_line(&entry, &prefix)?;
+/// Walk `path` once and collect every listed entry into a [`Tree`], so that rendering and
+/// anything needing global knowledge can work without touching the filesystem again.
+pub fn build_tree(path: &Path, config: &Config) -> Tree {
+    build_tree_with_progress(path, config, &mut || {})
+}
+
+/// Same as [`build_tree`], calling `on_entry` after each entry is read so that callers can
+/// report progress on long scans.
+pub fn build_tree_with_progress(path: &Path, config: &Config, on_entry: &mut dyn FnMut()) -> Tree {
+    match build_tree_within(path, config, usize::MAX, on_entry) {
+        Ok(tree) => tree,
+        Err(_) => unreachable!("a scan without a memory limit always completes"),
+    }
+}
+
+/// Same as [`build_tree_with_progress`], but stop collecting once the tree would take more
+/// than `limit` bytes and return the entries read so far as a [`StreamingScan`] that reads the
+/// rest one entry at a time.
+pub fn build_tree_within(
+    path: &Path,
+    config: &Config,
+    limit: usize,
+    on_entry: &mut dyn FnMut(),
+) -> Result<Tree, Box<StreamingScan>> {
+    let started = Instant::now();
+    // 栈中第 n 个元素是当前路径上第 n 层的目录
+    let mut stack: Vec<Node> = Vec::new();
+    let mut used = 0;
+
+    let mut iterator = get_iterator(path, &config.traversal, &config.filter);
+    for entry in iterator.by_ref() {
+        on_entry();
+        used += footprint(&entry);
+        attach_until(&mut stack, entry.level);
+        stack.push(Node::new(entry));
+        if used > limit {
+            return Err(Box::new(StreamingScan::resume(stack, iterator, started)));
         }
-        summary.num_folders = summary.num_folders.saturating_sub(1);
-        Ok(summary)
+    }
+    attach_until(&mut stack, 1);
+
+    let mut root = stack
+        .pop()
+        .unwrap_or_else(|| Node::new(FileItem::new(path, 0, true, config.traversal.follow_links)));
+    if config.filter.empty_dirs_only {
+        retain_empty_dirs(&mut root);
+    }
+    if config.filter.min_depth > 1 {
+        lift_to_min_depth(&mut root, config.filter.min_depth);
+    }
+
+    let mut summary = DirSummary::init();
+    let mut linked_files: HashSet<(u64, u64)> = HashSet::new();
+    summarize(&root, &mut summary, &mut linked_files);
+    summary.num_hidden += iterator.inner().skipped_hidden();
+    let timings = iterator.inner().timings();
+    aggregate_size(&mut root);
+    // 遍历时已经按名称排好，其他键需要目录的大小和条目数，建好树之后再排
+    let keys = &config.traversal.sort_keys;
+    if keys.iter().any(|key| *key != SortKey::Name) {
+        let names = if config.traversal.locale_sort {
+            NameOrder::from_env()
+        } else {
+            NameOrder::Bytewise
+        };
+        let order = SortOrder {
+            keys,
+            dirs_first: config.traversal.dirs_first,
+            names: &names,
+            time_kind: config.display.time_kind,
+        };
+        sort_tree(&mut root, &order);
+    }
+    if config.display.inode_usage {
+        // 根目录本身不计入统计
+        summary.total_inodes = aggregate_inodes(&mut root, &mut HashSet::new()) - 1;
+    }
+    // 统计在分组之前完成，报告中仍然是实际的文件数
+    if config.display.group_by_ext {
+        group_by_extension(&mut root);
+    }
+    summary.elapsed = started.elapsed();
+    Ok(Tree {
+        root,
+        summary,
+        timings,
+    })
+}
+
+/// 一个节点在内存中大致占用的字节数，不算同一目录下各条目共享的目录路径
+fn footprint(item: &FileItem) -> usize {
+    std::mem::size_of::<Node>() + item.file_name.len()
+}
+
+/// A scan that hands out entries in listing order as they are read instead of keeping them
+/// in a [`Tree`], for trees too large to hold in memory. Entries come in the traversal order
+/// (by name, directories first with `--dirsfirst`), and [`FileItem::is_last`] tells where a
+/// directory's entries end.
+///
+/// Nothing is aggregated: directories have no total size, `--sort` keys other than the name
+/// are ignored, and directories that are only listed for what they contain (with `-P`, or
+/// excluded by `-E` but read for a `!` rule) are listed even when nothing in them is.
+pub struct StreamingScan {
+    /// 超出内存上限之前已经读取的条目
+    pending: VecDeque<FileItem>,
+    iterator: FilteredIterator,
+    summary: DirSummary,
+    linked_files: HashSet<(u64, u64)>,
+    started: Instant,
+}
+
+impl StreamingScan {
+    /// Stream `path` from the start.
+    pub fn new(path: &Path, config: &Config) -> StreamingScan {
+        let iterator = get_iterator(path, &config.traversal, &config.filter);
+        StreamingScan::resume(Vec::new(), iterator, Instant::now())
     }
 
-    fn cal_symbol_switch(&self, symbol_switch_list: &mut Vec<bool>, level: usize, is_last: bool) {
-        while symbol_switch_list.len() > level {
-            symbol_switch_list.pop();
+    /// 接着 `stack` 中已经读取的部分继续；不再等到目录下出现列出的条目才列出目录
+    fn resume(stack: Vec<Node>, mut iterator: FilteredIterator, started: Instant) -> StreamingScan {
+        let mut pending = VecDeque::new();
+        // 栈中每个目录已经挂上的子树都在下一层的目录之前，因此都不是最后一个
+        for node in stack {
+            pending.push_back(node.item);
+            for child in node.children {
+                flatten(child, false, &mut pending);
+            }
         }
-        if level > symbol_switch_list.len() {
-            symbol_switch_list.push(true);
+        iterator.skip_filter();
+        StreamingScan {
+            pending,
+            iterator,
+            summary: DirSummary::init(),
+            linked_files: HashSet::new(),
+            started,
         }
-        let levels_len = symbol_switch_list.len();
-        if levels_len > 0 {
-            symbol_switch_list[levels_len.saturating_sub(1)] = !is_last;
+    }
+
+    /// The counts for the report, once every entry has been handed out.
+    pub fn finish(mut self) -> (DirSummary, ScanTimings) {
+        self.summary.num_hidden += self.iterator.inner().skipped_hidden();
+        self.summary.elapsed = self.started.elapsed();
+        (self.summary, self.iterator.inner().timings())
+    }
+}
+
+impl Iterator for StreamingScan {
+    type Item = FileItem;
+
+    fn next(&mut self) -> Option<FileItem> {
+        let item = self.pending.pop_front().or_else(|| self.iterator.next())?;
+        // 根目录本身不计入统计
+        if item.level > 0 {
+            count_entry(&item, &mut self.summary, &mut self.linked_files);
         }
+        self.summary.max_depth = self.summary.max_depth.max(item.level);
+        Some(item)
     }
+}
 
-    fn get_iterator(&self, path: &Path) -> FilteredIterator {
-        let list = FileIterator::new(path, &self.config);
-        let mut list = FilteredIterator::new(list);
-        if self.config.include_glob.is_none() {
-            list.skip_filter();
+/// 按先序展开子树；过滤掉的目录可能是遍历时标记的最后一个条目，按实际的子条目重新标记
+fn flatten(mut node: Node, is_last: bool, items: &mut VecDeque<FileItem>) {
+    node.item.is_last = is_last;
+    items.push_back(node.item);
+    let count = node.children.len();
+    for (index, child) in node.children.into_iter().enumerate() {
+        flatten(child, index + 1 == count, items);
+    }
+}
+
+fn summarize(node: &Node, summary: &mut DirSummary, linked_files: &mut HashSet<(u64, u64)>) {
+    // 根目录本身不计入统计
+    if node.item.level > 0 {
+        count_entry(&node.item, summary, linked_files);
+    }
+    summary.max_depth = summary.max_depth.max(node.item.level);
+    for child in &node.children {
+        summarize(child, summary, linked_files);
+    }
+}
+
+/// 自底向上累加目录大小
+fn aggregate_size(node: &mut Node) -> u64 {
+    node.size = if node.item.is_dir() {
+        node.children.iter_mut().map(aggregate_size).sum()
+    } else {
+        node.item.metadata.as_ref().map_or(0, |m| m.len())
+    };
+    node.size
+}
+
+/// 自底向上累加 inode 数，硬链接只在第一次出现时计入
+fn aggregate_inodes(node: &mut Node, linked_files: &mut HashSet<(u64, u64)>) -> u64 {
+    let item = &node.item;
+    let own = match item.metadata {
+        Ok(ref metadata) if has_hard_links(metadata) && !metadata.is_dir() => {
+            file_id(metadata).is_none_or(|id| linked_files.insert(id)) as u64
         }
-        list
+        _ => 1,
+    };
+    let below = item
+        .below_cutoff
+        .map_or(0, |below| (below.files + below.dirs) as u64);
+    let children: u64 = node
+        .children
+        .iter_mut()
+        .map(|child| aggregate_inodes(child, linked_files))
+        .sum();
+    node.inodes = own + below + children;
+    node.inodes
+}
+
+/// 去掉 `min_depth` 以上的层级，这一层的条目直接挂到根目录下，名字显示为相对根目录的路径
+fn lift_to_min_depth(root: &mut Node, min_depth: usize) {
+    let mut lifted = Vec::new();
+    let path: Arc<Path> = Arc::from(root.item.path());
+    for child in std::mem::take(&mut root.children) {
+        collect_at_depth(child, min_depth, &path, &mut lifted);
     }
+    root.children = lifted;
+}
+
+fn collect_at_depth(mut node: Node, min_depth: usize, root: &Arc<Path>, lifted: &mut Vec<Node>) {
+    if node.item.level < min_depth {
+        for child in node.children {
+            collect_at_depth(child, min_depth, root, lifted);
+        }
+        return;
+    }
+    node.item.show_relative_to(root);
+    lifted.push(node);
+}
+
+/// `--sort` 排列子条目的依据
+struct SortOrder<'a> {
+    keys: &'a [SortKey],
+    dirs_first: bool,
+    names: &'a NameOrder,
+    /// `mtime` 键比较的是 `--time` 选择的时间
+    time_kind: TimeKind,
+}
 
-    fn print_line(&mut self, entry: &FileItem, prefix: &str) -> io::Result<()> {
-        print!("{}", prefix);
-        if let Ok(ref metadata) = entry.metadata {
-            print_path(&entry.file_name, metadata, self.term, &self.config)?;
+/// 按 `--sort` 的各个键依次比较后排列子条目；返回 `node` 下的条目数，供 `entries` 使用
+fn sort_tree(node: &mut Node, order: &SortOrder) -> usize {
+    let SortOrder {
+        keys,
+        dirs_first,
+        names,
+        time_kind,
+    } = *order;
+    let mut counts: Vec<(usize, Node)> = std::mem::take(&mut node.children)
+        .into_iter()
+        .map(|mut child| (sort_tree(&mut child, order), child))
+        .collect();
+    counts.sort_by(|(count_a, a), (count_b, b)| {
+        let dirs = if dirs_first {
+            b.item.is_dir().cmp(&a.item.is_dir())
         } else {
-            print!("{} [Error File]", entry.file_name);
+            Ordering::Equal
+        };
+        keys.iter()
+            .fold(dirs, |order, key| {
+                order.then_with(|| match key {
+                    SortKey::Name => names.compare(&a.item.file_name, &b.item.file_name),
+                    SortKey::Entries => count_b.cmp(count_a),
+                    SortKey::Size => b.size.cmp(&a.size),
+                    SortKey::Mtime => time(b, time_kind).cmp(&time(a, time_kind)),
+                })
+            })
+            // 所有键都相同时按名称，再按字节，保证每次的顺序都一样
+            .then_with(|| names.compare(&a.item.file_name, &b.item.file_name))
+            .then_with(|| a.item.file_name.cmp(&b.item.file_name))
+    });
+    let below = node
+        .item
+        .below_cutoff
+        .map_or(0, |below| below.files + below.dirs);
+    let total = counts.iter().map(|(count, _)| count + 1).sum::<usize>() + below;
+    node.children = counts.into_iter().map(|(_, child)| child).collect();
+    total
+}
+
+fn time(node: &Node, kind: TimeKind) -> Option<SystemTime> {
+    kind.of(node.item.metadata.as_ref().ok()?)
+}
+
+/// 只保留空目录以及通往它们的父目录
+/// 去掉只为容纳其他条目而读取、最后其中没有任何条目的目录
+fn retain_empty_dirs(node: &mut Node) {
+    node.children.retain_mut(|child| {
+        if !child.item.is_dir() {
+            return false;
+        }
+        if child.children.is_empty() {
+            return child.item.dir_error.is_none() && is_empty_dir(&child.item.path());
+        }
+        retain_empty_dirs(child);
+        !child.children.is_empty()
+    });
+}
+
+/// 把每个目录下扩展名相同的多个文件合并为一个虚拟节点，放在第一个文件的位置
+fn group_by_extension(node: &mut Node) {
+    let children = std::mem::take(&mut node.children);
+    let mut counts: HashMap<OsString, usize> = HashMap::new();
+    for child in &children {
+        if let Some(ext) = group_key(&child.item) {
+            *counts.entry(ext.to_owned()).or_default() += 1;
+        }
+    }
+    // 扩展名对应的虚拟节点在 children 中的位置
+    let mut groups: HashMap<OsString, usize> = HashMap::new();
+    for mut child in children {
+        match group_key(&child.item).filter(|ext| counts[*ext] > 1) {
+            Some(ext) => {
+                if let Some(&index) = groups.get(ext) {
+                    node.children[index].size += child.size;
+                } else if let Ok(ref metadata) = child.item.metadata {
+                    let level = child.item.level;
+                    let group = FileItem::group(
+                        &node.item.path(),
+                        ext,
+                        level,
+                        metadata.clone(),
+                        counts[ext],
+                    );
+                    groups.insert(ext.to_owned(), node.children.len());
+                    node.children.push(Node {
+                        size: child.size,
+                        ..Node::new(group)
+                    });
+                }
+            }
+            None => {
+                group_by_extension(&mut child);
+                node.children.push(child);
+            }
+        }
+    }
+}
+
+/// 可以合并的文件的扩展名
+fn group_key(item: &FileItem) -> Option<&OsStr> {
+    if item.metadata.is_err() || item.is_dir() {
+        return None;
+    }
+    Path::new(&item.file_name).extension()
+}
+
+/// 将栈顶的节点挂到父节点下，直到栈中只剩 `level` 个元素
+fn attach_until(stack: &mut Vec<Node>, level: usize) {
+    while stack.len() > level {
+        let node = stack.pop().unwrap();
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => {
+                stack.push(node);
+                break;
+            }
         }
-        println!();
-        Ok(())
+    }
+}
+
+fn get_iterator(
+    path: &Path,
+    traversal: &TraversalOptions,
+    filter: &FilterOptions,
+) -> FilteredIterator {
+    let list = FileIterator::new(path, traversal, filter);
+    FilteredIterator::new(list, filter.include_glob.is_some())
+}
+
+fn count_entry(entry: &FileItem, summary: &mut DirSummary, linked_files: &mut HashSet<(u64, u64)>) {
+    summary.kinds.count(entry);
+    if entry.is_hidden() {
+        summary.num_hidden += 1;
+    }
+    if entry.metadata.is_err() || entry.dir_error.is_some() {
+        summary.num_errors += 1;
+    }
+    if let Some(below) = entry.below_cutoff {
+        summary.num_folders += below.dirs;
+        summary.num_files += below.files;
+    }
+    if entry.is_dir() {
+        summary.num_folders += 1;
+        return;
+    }
+    if entry.is_symlink {
+        summary.num_symlinks += 1;
+    } else {
+        summary.num_files += 1;
+    }
+    if let Ok(ref metadata) = entry.metadata {
+        // 硬链接指向同一个 inode，只统计一次
+        let counted =
+            !has_hard_links(metadata) || file_id(metadata).is_none_or(|id| linked_files.insert(id));
+        if counted {
+            summary.total_size += metadata.len();
+            summary.total_allocated += allocated_size(metadata);
+        }
+    }
+}
+
+/// 结束时输出的统计信息的详细程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportStyle {
+    /// Directory and file counts only
+    Short,
+    /// Also bytes, symlinks, hidden entries, errors and elapsed time
+    Long,
+}
+
+/// 按类型统计的条目数，`--report-types` 使用
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KindCounts {
+    pub regular: usize,
+    /// 同时计入 `regular`
+    pub executables: usize,
+    pub directories: usize,
+    /// 跟随链接时同时按目标的类型计入其他类别
+    pub symlinks: usize,
+    pub fifos: usize,
+    pub sockets: usize,
+    /// 字符设备和块设备
+    pub devices: usize,
+}
+
+impl KindCounts {
+    fn count(&mut self, entry: &FileItem) {
+        if entry.is_symlink {
+            self.symlinks += 1;
+        }
+        let Ok(ref metadata) = entry.metadata else {
+            return;
+        };
+        if metadata.is_dir() {
+            self.directories += 1;
+        } else if let Some(kind) = special_kind(metadata) {
+            match kind {
+                SpecialKind::Fifo => self.fifos += 1,
+                SpecialKind::Socket => self.sockets += 1,
+                SpecialKind::CharDevice | SpecialKind::BlockDevice => self.devices += 1,
+            }
+        } else if metadata.is_file() {
+            self.regular += 1;
+            if is_executable(&entry.display_name(), metadata) {
+                self.executables += 1;
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &KindCounts) {
+        self.regular += other.regular;
+        self.executables += other.executables;
+        self.directories += other.directories;
+        self.symlinks += other.symlinks;
+        self.fifos += other.fifos;
+        self.sockets += other.sockets;
+        self.devices += other.devices;
     }
 }
 
 pub struct DirSummary {
     pub num_folders: usize,
     pub num_files: usize,
+    pub total_size: u64,
+    /// 占用的磁盘空间，见 [`allocated_size`]
+    pub total_allocated: u64,
+    /// `--inode-usage` 时所有条目占用的 inode 数
+    pub total_inodes: u64,
+    pub kinds: KindCounts,
+    /// 不计入 `num_files` 的符号链接；与 GNU tree 一样，`-l` 时指向目录的链接算作目录
+    pub num_symlinks: usize,
+    pub num_hidden: usize,
+    pub num_errors: usize,
+    /// 列出的条目中最深的层级，根目录为 0
+    pub max_depth: usize,
+    pub elapsed: Duration,
 }
 
 impl DirSummary {
@@ -81,6 +575,31 @@ impl DirSummary {
         DirSummary {
             num_folders: 0,
             num_files: 0,
+            total_size: 0,
+            total_allocated: 0,
+            total_inodes: 0,
+            kinds: KindCounts::default(),
+            num_symlinks: 0,
+            num_hidden: 0,
+            num_errors: 0,
+            max_depth: 0,
+            elapsed: Duration::ZERO,
         }
     }
+
+    /// Add the counts of another root to these. Hard links shared between the two roots are
+    /// counted in both totals.
+    pub fn merge(&mut self, other: &DirSummary) {
+        self.num_folders += other.num_folders;
+        self.num_files += other.num_files;
+        self.total_size += other.total_size;
+        self.total_allocated += other.total_allocated;
+        self.total_inodes += other.total_inodes;
+        self.kinds.merge(&other.kinds);
+        self.num_symlinks += other.num_symlinks;
+        self.num_hidden += other.num_hidden;
+        self.num_errors += other.num_errors;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.elapsed += other.elapsed;
+    }
 }
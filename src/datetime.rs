@@ -0,0 +1,59 @@
+use std::fs::Metadata;
+use std::time::SystemTime;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+
+/// 与 GNU tree `-D` 相同的默认时间格式
+pub const DEFAULT_TIME_FORMAT: &str = "%b %e %H:%M";
+
+/// Check that `format` only contains strftime specifiers chrono understands.
+pub fn is_valid_format(format: &str) -> bool {
+    StrftimeItems::new(format).all(|item| !matches!(item, Item::Error))
+}
+
+/// Render `time` in the local timezone using a strftime-style `format`.
+pub fn format_time(time: SystemTime, format: &str) -> String {
+    DateTime::<Local>::from(time).format(format).to_string()
+}
+
+/// `-D` 显示的是哪一个时间戳
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum TimeKind {
+    /// Last modification
+    #[default]
+    Mtime,
+    /// Last status change (inode change on Unix)
+    Ctime,
+    /// Last access
+    Atime,
+    /// Creation (birth) time, where the filesystem records it
+    Created,
+}
+
+impl TimeKind {
+    /// 平台或文件系统不支持时返回 `None`
+    pub fn of(self, metadata: &Metadata) -> Option<SystemTime> {
+        match self {
+            TimeKind::Mtime => metadata.modified().ok(),
+            TimeKind::Ctime => status_changed(metadata),
+            TimeKind::Atime => metadata.accessed().ok(),
+            TimeKind::Created => metadata.created().ok(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn status_changed(metadata: &Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    use std::time::{Duration, UNIX_EPOCH};
+    let secs = u64::try_from(metadata.ctime()).ok()?;
+    let nanos = u32::try_from(metadata.ctime_nsec()).ok()?;
+    UNIX_EPOCH.checked_add(Duration::new(secs, nanos))
+}
+
+#[cfg(not(unix))]
+fn status_changed(_metadata: &Metadata) -> Option<SystemTime> {
+    None
+}
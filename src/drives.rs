@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+
+/// The root of every drive letter currently assigned, such as `C:\`.
+#[cfg(windows)]
+pub fn logical_drives() -> Vec<PathBuf> {
+    use windows_sys::Win32::Storage::FileSystem::GetLogicalDrives;
+    // 每一位对应一个盘符，第 0 位是 A:
+    let mask = unsafe { GetLogicalDrives() };
+    (0..26u8)
+        .filter(|bit| mask & (1 << bit) != 0)
+        .map(|bit| PathBuf::from(format!("{}:\\", (b'A' + bit) as char)))
+        .collect()
+}
+
+/// 只有 Windows 有盘符
+#[cfg(not(windows))]
+pub fn logical_drives() -> Vec<PathBuf> {
+    Vec::new()
+}
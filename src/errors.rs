@@ -0,0 +1,84 @@
+use std::io;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::core::{Node, Tree};
+use crate::file_iterator::{escape_name, FileItem};
+
+/// `--errors` 的取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ErrorFormat {
+    /// Mark failed entries in the tree only
+    #[default]
+    Text,
+    /// Also print one JSON object per error on stderr
+    Json,
+}
+
+/// One failure met while walking the tree.
+#[derive(Debug, Serialize)]
+pub struct TraversalError {
+    pub path: String,
+    /// 失败的操作：`stat`、`read_dir` 或 `link_target`
+    pub operation: &'static str,
+    /// snake_case 形式的 `io::ErrorKind`，例如 `permission_denied`
+    pub kind: String,
+    pub message: String,
+}
+
+impl TraversalError {
+    fn new(item: &FileItem, operation: &'static str, err: &io::Error) -> TraversalError {
+        TraversalError {
+            path: escape_name(item.path().as_os_str()).into_owned(),
+            operation,
+            kind: kind_name(err.kind()),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Collect every error recorded on the entries of `tree`, in listing order.
+pub fn traversal_errors(tree: &Tree) -> Vec<TraversalError> {
+    let mut errors = Vec::new();
+    collect(&tree.root, &mut errors);
+    errors
+}
+
+/// The errors recorded on one entry, for entries streamed without building a [`Tree`].
+pub fn item_errors(item: &FileItem) -> Vec<TraversalError> {
+    let mut errors = Vec::new();
+    if let Err(err) = &item.metadata {
+        errors.push(TraversalError::new(item, "stat", err));
+    }
+    if let Some(err) = &item.target_error {
+        errors.push(TraversalError::new(item, "link_target", err));
+    }
+    if let Some(err) = &item.dir_error {
+        errors.push(TraversalError::new(item, "read_dir", err));
+    }
+    errors
+}
+
+fn collect(node: &Node, errors: &mut Vec<TraversalError>) {
+    errors.extend(item_errors(&node.item));
+    for child in &node.children {
+        collect(child, errors);
+    }
+}
+
+/// `PermissionDenied` -> `permission_denied`
+fn kind_name(kind: io::ErrorKind) -> String {
+    let mut name = String::new();
+    for (i, c) in format!("{:?}", kind).chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
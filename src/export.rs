@@ -0,0 +1,329 @@
+use std::fmt::{self, Write};
+use std::io;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{DirSummary, Node, Traversal, Tree};
+use crate::datetime::format_time;
+use crate::file_iterator::{escape_name, FileItem};
+use crate::mtree::render_mtree;
+use crate::symbol::{special_kind, SpecialKind};
+
+/// A machine-readable output format at a fixed schema version. The columns or keys of a given
+/// `name@version` never change; incompatible changes get a new version next to the old one.
+#[derive(Debug)]
+pub struct OutputFormat {
+    pub name: &'static str,
+    pub version: u32,
+    pub description: &'static str,
+    render: fn(&Tree, Option<&str>, Traversal) -> String,
+}
+
+/// 所有可以用 `--output-format` 选择的格式，同名的按版本从低到高排列
+pub static OUTPUT_FORMATS: &[OutputFormat] = &[
+    OutputFormat {
+        name: "csv",
+        version: 1,
+        description: "header `path,name,type,size,depth,parent,modified`, one row per entry",
+        render: render_csv,
+    },
+    OutputFormat {
+        name: "ndjson",
+        version: 1,
+        description: "one object per entry with the same keys as csv@1",
+        render: render_ndjson,
+    },
+    OutputFormat {
+        name: "json",
+        version: 1,
+        description: "one document: `version`, nested `tree` with `children`, and `report`",
+        render: render_json,
+    },
+    OutputFormat {
+        name: "mtree",
+        version: 1,
+        description: "BSD mtree spec with type, mode, size, time, link and sha256digest",
+        render: render_mtree,
+    },
+];
+
+impl OutputFormat {
+    /// Look up `name` or `name@version`; a bare name selects the latest version.
+    pub fn parse(spec: &str) -> Result<&'static OutputFormat, FormatError> {
+        let (name, version) = match spec.split_once('@') {
+            Some((name, version)) => {
+                let version = version
+                    .parse::<u32>()
+                    .map_err(|_| FormatError::InvalidVersion(spec.to_string()))?;
+                (name, Some(version))
+            }
+            None => (spec, None),
+        };
+        let mut candidates = OUTPUT_FORMATS.iter().filter(|f| f.name == name).peekable();
+        if candidates.peek().is_none() {
+            return Err(FormatError::UnknownName(name.to_string()));
+        }
+        match version {
+            Some(version) => candidates
+                .find(|f| f.version == version)
+                .ok_or_else(|| FormatError::UnknownVersion(spec.to_string())),
+            None => Ok(candidates.last().unwrap()),
+        }
+    }
+
+    /// `name@version`，输出中用来标明格式
+    pub fn spec(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+
+    /// Render `tree`; flat formats list the entries in the order of `traversal`, nested ones
+    /// ignore it.
+    pub fn render(&self, tree: &Tree, time_format: Option<&str>, traversal: Traversal) -> String {
+        (self.render)(tree, time_format, traversal)
+    }
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    UnknownName(String),
+    UnknownVersion(String),
+    InvalidVersion(String),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::UnknownName(name) => write!(f, "unknown format '{}'", name)?,
+            FormatError::UnknownVersion(spec) => write!(f, "unknown format version '{}'", spec)?,
+            FormatError::InvalidVersion(spec) => write!(f, "invalid format version '{}'", spec)?,
+        }
+        write!(f, "; available formats:")?;
+        for format in OUTPUT_FORMATS {
+            write!(f, "\n  {:<10}{}", format.spec(), format.description)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// 扁平输出中的一行，NDJSON 清单读回来时也使用同一结构
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Row {
+    pub path: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub size: u64,
+    pub depth: usize,
+    pub parent: String,
+    pub modified: Option<String>,
+}
+
+impl Row {
+    pub fn new(item: &FileItem, time_format: Option<&str>) -> Row {
+        let metadata = item.metadata.as_ref().ok();
+        let modified = metadata
+            .and_then(|m| m.modified().ok())
+            .map(|time| match time_format {
+                Some(format) => format_time(time, format),
+                None => time
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs())
+                    .to_string(),
+            });
+        Row {
+            path: escape_name(item.path().as_os_str()).into_owned(),
+            name: item.display_name().into_owned(),
+            kind: entry_kind(item).to_string(),
+            size: metadata.map_or(0, |m| m.len()),
+            depth: item.level,
+            parent: item
+                .path()
+                .parent()
+                .filter(|_| item.level > 0)
+                .map_or_else(String::new, |p| escape_name(p.as_os_str()).into_owned()),
+            modified,
+        }
+    }
+}
+
+/// 读回 ndjson 输出，每行一个 [`Row`]，空行跳过
+pub fn read_rows(text: &str) -> io::Result<Vec<Row>> {
+    let mut rows = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row = serde_json::from_str(line).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", index + 1, err),
+            )
+        })?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// `type` of an entry in the exports: `directory`, `file`, `link`, `error`, or for special
+/// files `fifo`, `socket`, `char` or `block` like mtree.
+pub fn entry_kind(item: &FileItem) -> &'static str {
+    match item.metadata {
+        Err(_) => "error",
+        Ok(_) if item.is_symlink && !item.is_dir() => "link",
+        Ok(ref metadata) if metadata.is_dir() => "directory",
+        Ok(ref metadata) => match special_kind(metadata) {
+            Some(SpecialKind::Fifo) => "fifo",
+            Some(SpecialKind::Socket) => "socket",
+            Some(SpecialKind::CharDevice) => "char",
+            Some(SpecialKind::BlockDevice) => "block",
+            None => "file",
+        },
+    }
+}
+
+/// 每个条目一行，带上深度和父目录，不解析前缀也能还原层级
+fn render_csv(tree: &Tree, time_format: Option<&str>, traversal: Traversal) -> String {
+    let mut output = String::from("path,name,type,size,depth,parent,modified\n");
+    for node in tree.nodes(traversal) {
+        write_csv(&Row::new(&node.item, time_format), &mut output);
+    }
+    output
+}
+
+fn render_ndjson(tree: &Tree, time_format: Option<&str>, traversal: Traversal) -> String {
+    let mut output = String::new();
+    for node in tree.nodes(traversal) {
+        write_ndjson(&Row::new(&node.item, time_format), &mut output);
+    }
+    output
+}
+
+/// json@1 的顶层结构，`compare` 读回来时也使用同一结构
+#[derive(Serialize, Deserialize)]
+pub struct JsonDocument {
+    pub version: u32,
+    pub tree: JsonNode,
+    pub report: JsonReport,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JsonNode {
+    pub path: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub size: u64,
+    pub modified: Option<String>,
+    /// 只有目录才有这个键
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<JsonNode>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JsonReport {
+    pub directories: usize,
+    pub files: usize,
+    pub size: u64,
+}
+
+fn json_node(node: &Node, time_format: Option<&str>) -> JsonNode {
+    let row = Row::new(&node.item, time_format);
+    JsonNode {
+        path: row.path,
+        name: row.name,
+        kind: row.kind,
+        size: row.size,
+        modified: row.modified,
+        children: node.item.is_dir().then(|| {
+            node.children
+                .iter()
+                .map(|child| json_node(child, time_format))
+                .collect()
+        }),
+    }
+}
+
+fn render_json(tree: &Tree, time_format: Option<&str>, _traversal: Traversal) -> String {
+    let document = JsonDocument {
+        version: 1,
+        tree: json_node(&tree.root, time_format),
+        report: JsonReport {
+            directories: tree.summary.num_folders,
+            files: tree.summary.num_files,
+            size: tree.summary.total_size,
+        },
+    };
+    let mut output = serde_json::to_string_pretty(&document).unwrap_or_default();
+    output.push('\n');
+    output
+}
+
+/// `--summary-json` 输出的对象，键只增不改
+#[derive(Serialize)]
+struct SummaryDocument {
+    version: u32,
+    roots: Vec<String>,
+    directories: usize,
+    files: usize,
+    size: u64,
+    allocated: u64,
+    symlinks: usize,
+    hidden: usize,
+    errors: usize,
+    elapsed_seconds: f64,
+}
+
+/// The report of one run as a single-line JSON object, for jobs that track directory growth.
+pub fn summary_json(roots: &[PathBuf], summary: &DirSummary) -> String {
+    let document = SummaryDocument {
+        version: 1,
+        roots: roots
+            .iter()
+            .map(|root| escape_name(root.as_os_str()).into_owned())
+            .collect(),
+        directories: summary.num_folders,
+        files: summary.num_files,
+        size: summary.total_size,
+        allocated: summary.total_allocated,
+        symlinks: summary.num_symlinks,
+        hidden: summary.num_hidden,
+        errors: summary.num_errors,
+        elapsed_seconds: summary.elapsed.as_secs_f64(),
+    };
+    let mut output = serde_json::to_string(&document).unwrap_or_default();
+    output.push('\n');
+    output
+}
+
+fn write_csv(row: &Row, output: &mut String) {
+    let _ = writeln!(
+        output,
+        "{},{},{},{},{},{},{}",
+        csv_field(&row.path),
+        csv_field(&row.name),
+        row.kind,
+        row.size,
+        row.depth,
+        csv_field(&row.parent),
+        csv_field(row.modified.as_deref().unwrap_or_default())
+    );
+}
+
+fn write_ndjson(row: &Row, output: &mut String) {
+    if let Ok(line) = serde_json::to_string(row) {
+        output.push_str(&line);
+        output.push('\n');
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
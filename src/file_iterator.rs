@@ -1,99 +1,541 @@
-use std::collections::VecDeque;
+use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
+use std::ffi::{OsStr, OsString};
+use std::fmt::Write;
 use std::fs::{DirEntry, Metadata};
-use std::path::{Path, PathBuf};
-use std::{fs, io};
+use std::path::{Component, Path, PathBuf, Prefix};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use std::{fs, io, thread};
 
-use crate::Config;
-use globset::GlobMatcher;
+use crate::filter::ExcludeRules;
+use crate::git::TrackedFiles;
+use crate::hidden::is_hidden;
+use crate::pattern::Pattern;
+use crate::sort::NameOrder;
+use crate::{FilterOptions, TraversalOptions};
 
 #[derive(Debug)]
 pub struct FileItem {
-    pub file_name: String,
-    pub path: PathBuf,
+    /// 不会再增长，不需要 `OsString` 预留的容量
+    pub file_name: Box<OsStr>,
+    /// 所在目录的路径，同一目录下的条目共享一份；没有上级的根路径（`/`、`.`）为 `None`，
+    /// 此时 `file_name` 就是完整的路径
+    dir: Option<Arc<Path>>,
     pub metadata: io::Result<Metadata>,
     pub level: usize,
     pub is_last: bool,
+    /// 目录已经访问过（符号链接形成了环），不再展开
+    pub recursive: bool,
+    /// 条目本身是符号链接（跟随链接时 metadata 是目标的信息）
+    pub is_symlink: bool,
+    /// 跟随链接时符号链接自身的信息
+    pub link_metadata: Option<Metadata>,
+    /// 跟随链接时读取目标信息失败的原因，此时 metadata 是链接自身的信息
+    pub target_error: Option<io::Error>,
+    /// 读取目录内容失败的原因
+    pub dir_error: Option<io::Error>,
+    /// 被 `-L` 截断的目录下没有显示出来的条目数
+    pub below_cutoff: Option<EntryCount>,
+    /// 被 `-L` 截断的目录下一层没有显示的条目数
+    pub not_shown: Option<usize>,
+    /// `--group-by-ext` 生成的虚拟节点合并的文件数
+    pub grouped: Option<usize>,
+    /// 目录被 `-E` 排除，只因为其中可能有被 `!` 重新包含的条目才读取
+    pub excluded: bool,
+    /// 目录本身不应列出，只为容纳其中列出的条目而显示，其中没有条目时去掉
+    pub container: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EntryCount {
+    pub files: usize,
+    pub dirs: usize,
+}
+
+/// 遍历中各阶段累计花费的时间，以及读取的目录数和读取元数据的条目数
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScanTimings {
+    pub read_dir: Duration,
+    pub stat: Duration,
+    pub filter: Duration,
+    pub dirs_read: usize,
+    pub stats: usize,
+    /// 读取元数据时最多同时使用的线程数
+    pub stat_threads: usize,
+}
+
+impl ScanTimings {
+    /// 累加另一个根目录的结果
+    pub fn merge(&mut self, other: &ScanTimings) {
+        self.read_dir += other.read_dir;
+        self.stat += other.stat;
+        self.filter += other.filter;
+        self.dirs_read += other.dirs_read;
+        self.stats += other.stats;
+        self.stat_threads = self.stat_threads.max(other.stat_threads);
+    }
 }
 
 impl FileItem {
-    pub fn new(path: &Path, level: usize, is_last: bool) -> FileItem {
-        let metadata = path.symlink_metadata();
+    pub fn new(path: &Path, level: usize, is_last: bool, follow_links: bool) -> FileItem {
+        let dir = path.file_name().and(path.parent()).map(Arc::from);
+        FileItem::read(dir, path, level, is_last, follow_links)
+    }
+
+    /// 目录 `dir` 中的条目，与同一目录下的其他条目共享 `dir`
+    fn in_dir(dir: &Arc<Path>, path: &Path, level: usize, follow_links: bool) -> FileItem {
+        FileItem::read(Some(Arc::clone(dir)), path, level, false, follow_links)
+    }
+
+    fn read(
+        dir: Option<Arc<Path>>,
+        path: &Path,
+        level: usize,
+        is_last: bool,
+        follow_links: bool,
+    ) -> FileItem {
+        let link_metadata = path.symlink_metadata();
+        let is_symlink = link_metadata
+            .as_ref()
+            .is_ok_and(|m| m.file_type().is_symlink());
+        let (metadata, own_metadata, target_error) = if follow_links && is_symlink {
+            match path.metadata() {
+                Ok(target) => (Ok(target), link_metadata.ok(), None),
+                Err(err) => (link_metadata, None, Some(err)),
+            }
+        } else {
+            (link_metadata, None, None)
+        };
         let file_name = path
             .file_name()
-            .and_then(|name| name.to_str())
-            .or_else(|| path.to_str())
-            .unwrap_or("");
+            .map_or_else(|| root_name(path).into_boxed_os_str(), Box::from);
 
         FileItem {
-            file_name: file_name.to_string(),
-            path: path.to_owned(),
+            file_name,
+            dir,
             metadata,
             level,
             is_last,
+            recursive: false,
+            is_symlink,
+            link_metadata: own_metadata,
+            target_error,
+            dir_error: None,
+            below_cutoff: None,
+            not_shown: None,
+            grouped: None,
+            excluded: false,
+            container: false,
+        }
+    }
+
+    /// 代表目录 `parent` 下 `count` 个扩展名为 `ext` 的文件的虚拟条目
+    pub fn group(
+        parent: &Path,
+        ext: &OsStr,
+        level: usize,
+        metadata: Metadata,
+        count: usize,
+    ) -> FileItem {
+        let mut file_name = OsString::from("*.");
+        file_name.push(ext);
+        FileItem {
+            file_name: file_name.into_boxed_os_str(),
+            dir: Some(Arc::from(parent)),
+            metadata: Ok(metadata),
+            level,
+            is_last: false,
+            recursive: false,
+            is_symlink: false,
+            link_metadata: None,
+            target_error: None,
+            dir_error: None,
+            below_cutoff: None,
+            not_shown: None,
+            grouped: Some(count),
+            excluded: false,
+            container: false,
+        }
+    }
+
+    /// 读取元数据超时、无法得知任何信息的条目
+    fn timed_out(dir: &Arc<Path>, path: &Path, level: usize) -> FileItem {
+        FileItem {
+            file_name: Box::from(path.file_name().unwrap_or_default()),
+            dir: Some(Arc::clone(dir)),
+            metadata: Err(io::ErrorKind::TimedOut.into()),
+            level,
+            is_last: false,
+            recursive: false,
+            is_symlink: false,
+            link_metadata: None,
+            target_error: None,
+            dir_error: None,
+            below_cutoff: None,
+            not_shown: None,
+            grouped: None,
+            excluded: false,
+            container: false,
+        }
+    }
+
+    /// The full path of the entry, joined from its directory and name on each call; entries
+    /// keep only their name so that a large tree does not hold every path in full.
+    pub fn path(&self) -> PathBuf {
+        match self.dir {
+            Some(ref dir) => dir.join(&*self.file_name),
+            None => PathBuf::from(&*self.file_name),
+        }
+    }
+
+    /// The directory the entry is in, shared with its siblings, or `None` for a root given
+    /// without a parent. Together with `file_name` it names the entry without building
+    /// [`FileItem::path`].
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// 改为显示相对 `root` 的路径，`--min-depth` 把深层的条目直接挂到根目录下时使用
+    pub fn show_relative_to(&mut self, root: &Arc<Path>) {
+        let path = self.path();
+        if let Ok(relative) = path.strip_prefix(root) {
+            self.file_name = Box::from(relative.as_os_str());
+            self.dir = Some(Arc::clone(root));
         }
     }
 
+    /// 用于显示的文件名，非 UTF-8 字节转义为 `\xNN`
+    pub fn display_name(&self) -> Cow<'_, str> {
+        escape_name(&self.file_name)
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        is_hidden(&self.file_name, self.metadata.as_ref().ok())
+    }
+
     pub fn is_dir(&self) -> bool {
         self.metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false)
     }
 }
 
+/// 决定一个条目是否列出的规则
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// 隐藏文件，没有 `-a`
+    Hidden,
+    /// `--tracked` 时不在 git 索引中
+    Untracked,
+    /// 位于 `--exclude-path` 之下
+    ExcludePath(PathBuf),
+    /// 最后匹配的 `-E` 规则排除了它
+    ExcludePattern(String),
+    /// 所在目录被 `-E` 排除，没有规则把它重新包含进来
+    InsideExcluded,
+    /// 最后匹配的是以 `!` 开头的 `-E` 规则
+    Reincluded(String),
+    /// `--hidden-only` 时不是隐藏条目
+    NotHidden,
+    /// 文件与 `-P` 比较的结果
+    IncludePattern(String),
+    /// 目录不与 `-P` 比较，没有匹配的文件时才会被去掉
+    Directory,
+    /// 没有任何规则排除它
+    Default,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    pub listed: bool,
+    pub rule: Rule,
+    /// 被排除的目录仍要读取，其中可能有被 `!` 重新包含的条目
+    pub container: bool,
+}
+
+impl Verdict {
+    fn listed(rule: Rule) -> Verdict {
+        Verdict {
+            listed: true,
+            rule,
+            container: false,
+        }
+    }
+
+    fn excluded(rule: Rule) -> Verdict {
+        Verdict {
+            listed: false,
+            rule,
+            container: false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct FileIterator {
     queue: VecDeque<FileItem>,
     show_hidden: bool,
+    hidden_only: bool,
     max_level: usize,
-    include_glob: Option<GlobMatcher>,
+    follow_links: bool,
+    count_below: bool,
+    show_truncated: bool,
+    parallel_stat: bool,
+    threads: usize,
+    timeout: Option<Duration>,
+    dirs_first: bool,
+    unsorted: bool,
+    /// 模式中的路径相对于根目录
+    root: PathBuf,
+    /// 根目录的绝对路径，用于和 `exclude_paths` 比较
+    absolute_root: PathBuf,
+    exclude_paths: Vec<PathBuf>,
+    include_glob: Option<Arc<Pattern>>,
+    exclude_glob: Arc<ExcludeRules>,
+    tracked: Option<TrackedFiles>,
+    name_order: NameOrder,
+    visited: HashSet<(u64, u64)>,
+    /// 没有 inode 编号的平台（Windows）改用规范化路径记录访问过的目录
+    visited_paths: HashSet<PathBuf>,
+    skipped_hidden: usize,
+    timings: ScanTimings,
 }
 
 impl FileIterator {
-    pub fn new(path: &Path, config: &Config) -> FileIterator {
+    pub fn new(path: &Path, traversal: &TraversalOptions, filter: &FilterOptions) -> FileIterator {
         let mut queue = VecDeque::new();
-        queue.push_back(FileItem::new(path, 0, true));
+        queue.push_back(FileItem::new(path, 0, true, traversal.follow_links));
         FileIterator {
             queue,
-            max_level: config.max_level,
-            show_hidden: config.show_all,
-            include_glob: config.include_glob.clone(),
+            max_level: traversal.max_level,
+            show_hidden: filter.show_all || filter.hidden_only,
+            hidden_only: filter.hidden_only,
+            follow_links: traversal.follow_links,
+            count_below: traversal.count_below,
+            show_truncated: traversal.show_truncated,
+            parallel_stat: traversal.parallel_stat,
+            threads: traversal.threads,
+            timeout: traversal.timeout,
+            dirs_first: traversal.dirs_first,
+            unsorted: traversal.unsorted,
+            root: path.to_path_buf(),
+            absolute_root: std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()),
+            exclude_paths: filter.exclude_paths.clone(),
+            include_glob: filter.include_glob.clone(),
+            exclude_glob: Arc::clone(&filter.exclude_glob),
+            tracked: filter.tracked.clone(),
+            name_order: if traversal.locale_sort {
+                NameOrder::from_env()
+            } else {
+                NameOrder::Bytewise
+            },
+            visited: HashSet::new(),
+            visited_paths: HashSet::new(),
+            skipped_hidden: 0,
+            timings: ScanTimings::default(),
+        }
+    }
+
+    /// 因为是隐藏文件而没有列出的条目数
+    pub fn skipped_hidden(&self) -> usize {
+        self.skipped_hidden
+    }
+
+    pub fn timings(&self) -> ScanTimings {
+        self.timings
+    }
+
+    /// `inside_excluded` 表示上级目录被 `-E` 排除
+    fn is_included(&mut self, item: &mut FileItem, inside_excluded: bool) -> bool {
+        let verdict = self.verdict(item, inside_excluded);
+        if verdict.rule == Rule::Hidden {
+            self.skipped_hidden += 1;
         }
+        item.container = verdict.container;
+        item.excluded = verdict.container && verdict.rule != Rule::NotHidden;
+        verdict.listed || verdict.container
     }
 
-    fn is_glob_included(&self, file_name: &str) -> bool {
-        if let Some(ref glob) = self.include_glob {
-            glob.is_match(file_name)
+    /// 依次检查各条过滤规则，返回第一条起决定作用的规则；不修改统计
+    pub fn verdict(&self, item: &FileItem, inside_excluded: bool) -> Verdict {
+        // 规则只看文件名时用文件名代替完整路径，不必为每个条目拼接一次
+        let path = if self.rules_need_paths() {
+            Cow::Owned(item.path())
         } else {
-            true
+            Cow::Borrowed(Path::new(&*item.file_name))
+        };
+        self.verdict_for(&path, item.is_dir(), item.is_hidden(), inside_excluded)
+    }
+
+    /// 是否有规则要用到条目的路径而不只是文件名
+    fn rules_need_paths(&self) -> bool {
+        self.tracked.is_some()
+            || !self.exclude_paths.is_empty()
+            || self.exclude_glob.matches_paths()
+            || self
+                .include_glob
+                .as_ref()
+                .is_some_and(|pattern| pattern.matches_path())
+    }
+
+    /// The verdict for an entry at `path` that does not have to exist, such as one recorded in
+    /// a manifest, given its type.
+    pub fn verdict_for(
+        &self,
+        path: &Path,
+        is_dir: bool,
+        hidden: bool,
+        inside_excluded: bool,
+    ) -> Verdict {
+        if !self.show_hidden && hidden {
+            return Verdict::excluded(Rule::Hidden);
+        }
+        let name = path.file_name().unwrap_or_default();
+        if let Some(ref tracked) = self.tracked {
+            if !tracked.contains(path, is_dir) {
+                return Verdict::excluded(Rule::Untracked);
+            }
+        }
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        if !self.exclude_paths.is_empty() {
+            let absolute = self.absolute_root.join(relative);
+            if let Some(prefix) = self.exclude_paths.iter().find(|p| absolute.starts_with(p)) {
+                return Verdict::excluded(Rule::ExcludePath(prefix.clone()));
+            }
+        }
+        let (excluded, rule) = self
+            .exclude_glob
+            .decide(name, relative, is_dir, inside_excluded);
+        if excluded {
+            let mut verdict = Verdict::excluded(match rule {
+                Some(pattern) => Rule::ExcludePattern(pattern.as_str().to_string()),
+                None => Rule::InsideExcluded,
+            });
+            verdict.container = is_dir && self.exclude_glob.may_reinclude_below(relative);
+            return verdict;
+        }
+        if self.hidden_only && !hidden {
+            let mut verdict = Verdict::excluded(Rule::NotHidden);
+            verdict.container = is_dir;
+            return verdict;
+        }
+        let default_rule = match rule {
+            Some(pattern) => Rule::Reincluded(pattern.as_str().to_string()),
+            None => Rule::Default,
+        };
+        match self.include_glob {
+            Some(_) if is_dir => Verdict::listed(Rule::Directory),
+            Some(ref pattern) => {
+                let rule = Rule::IncludePattern(pattern.as_str().to_string());
+                if pattern.is_match(name, relative, is_dir) {
+                    Verdict::listed(rule)
+                } else {
+                    Verdict::excluded(rule)
+                }
+            }
+            None => Verdict::listed(default_rule),
         }
     }
 
-    fn is_included(&self, name: &str, is_dir: bool) -> bool {
-        if !self.show_hidden && name.starts_with('.') {
-            return false;
+    /// Explain whether `path` would be listed. Its parent directories below the root are
+    /// checked first, since excluding a directory hides everything in it; the returned path is
+    /// the entry whose rule decided. `path` does not need to exist.
+    pub fn explain(&self, path: &Path) -> (PathBuf, Verdict) {
+        let mut parents: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| *dir != self.root && dir.starts_with(&self.root))
+            .collect();
+        parents.reverse();
+        // 最近一个被排除、但因为 `!` 规则仍被读取的上级目录
+        let mut excluded_parent: Option<(&Path, Verdict)> = None;
+        for dir in parents {
+            let inside_excluded = excluded_parent.is_some();
+            let verdict = self.verdict(
+                &FileItem::new(dir, 1, false, self.follow_links),
+                inside_excluded,
+            );
+            if verdict.container && verdict.rule != Rule::NotHidden {
+                if verdict.rule != Rule::InsideExcluded {
+                    excluded_parent = Some((dir, verdict));
+                }
+            } else if !verdict.listed && !verdict.container {
+                return (dir.to_path_buf(), verdict);
+            } else {
+                excluded_parent = None;
+            }
         }
-        if is_dir {
-            true
-        } else {
-            self.is_glob_included(name)
+        let item = FileItem::new(path, 1, false, self.follow_links);
+        let verdict = self.verdict(&item, excluded_parent.is_some());
+        match excluded_parent {
+            Some((dir, parent_verdict)) if verdict.rule == Rule::InsideExcluded => {
+                (dir.to_path_buf(), parent_verdict)
+            }
+            _ => (path.to_path_buf(), verdict),
         }
     }
 
-    fn push_dir(&mut self, item: &FileItem) {
-        let err_msg = format!(
-            "Couldn't retrieve files in directory: {}",
-            item.path.display()
-        );
-        let mut dir_entries: Vec<DirEntry> = fs::read_dir(&item.path)
-            .expect(&err_msg)
-            .into_iter()
-            .collect::<io::Result<Vec<_>>>()
-            .expect(&err_msg);
-        dir_entries.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+    /// 记录目录的 (device, inode)，如果之前已经访问过则返回 false
+    fn mark_visited(&mut self, item: &FileItem) -> bool {
+        if let Some(id) = item.metadata.as_ref().ok().and_then(file_id) {
+            return self.visited.insert(id);
+        }
+        // 规范化路径会解析所有符号链接，指向同一目录的不同路径得到相同的结果
+        match fs::canonicalize(item.path()) {
+            Ok(path) => self.visited_paths.insert(path),
+            Err(_) => true,
+        }
+    }
 
-        let mut entries: Vec<FileItem> = dir_entries
-            .iter()
-            .map(|e| FileItem::new(&e.path(), item.level + 1, false))
-            .filter(|item| self.is_included(&item.file_name, item.is_dir()))
-            .collect();
+    fn push_dir(&mut self, item: &FileItem) -> io::Result<()> {
+        let started = Instant::now();
+        // 目录下的条目共享这一份路径
+        let dir: Arc<Path> = Arc::from(item.path());
+        let mut dir_entries: Vec<DirEntry> = match self.timeout {
+            Some(timeout) => {
+                let path = Arc::clone(&dir);
+                run_with_timeout(timeout, move || {
+                    fs::read_dir(path)?.collect::<io::Result<Vec<_>>>()
+                })??
+            }
+            None => fs::read_dir(&dir)?.collect::<io::Result<Vec<_>>>()?,
+        };
+        // 队列从末尾取出，按名称倒序排列
+        if self.unsorted {
+            dir_entries.reverse();
+        } else {
+            dir_entries.sort_by(|a, b| self.name_order.compare(&b.file_name(), &a.file_name()));
+        }
+
+        let paths: Vec<PathBuf> = dir_entries.iter().map(DirEntry::path).collect();
+        let level = item.level + 1;
+        let read = Instant::now();
+        self.timings.read_dir += read - started;
+        self.timings.dirs_read += 1;
+        self.timings.stats += paths.len();
+        let threads = if self.parallel_stat { self.threads } else { 1 };
+        let mut entries: Vec<FileItem> = match self.timeout {
+            Some(timeout) => {
+                let (entries, workers) =
+                    stat_with_timeout(&dir, &paths, level, self.follow_links, timeout, threads);
+                self.timings.stat_threads = self.timings.stat_threads.max(workers);
+                entries
+            }
+            None => {
+                let (entries, workers) =
+                    stat_parallel(&dir, &paths, level, self.follow_links, threads);
+                self.timings.stat_threads = self.timings.stat_threads.max(workers);
+                entries
+            }
+        };
+        let stated = Instant::now();
+        self.timings.stat += stated - read;
+        let inside_excluded = item.excluded;
+        entries.retain_mut(|entry| self.is_included(entry, inside_excluded));
+        if self.dirs_first {
+            // 队列从末尾取出，目录要放在后面
+            entries.sort_by_key(FileItem::is_dir);
+        }
+        self.timings.filter += stated.elapsed();
 
         if let Some(item) = entries.first_mut() {
             item.is_last = true;
@@ -102,6 +544,52 @@ impl FileIterator {
         for item in entries {
             self.queue.push_back(item);
         }
+        Ok(())
+    }
+
+    /// `-L` 截断的目录下一层会被列出的条目数；`inside_excluded` 表示目录本身被 `-E` 排除
+    fn count_entries_at(&self, path: &Path, inside_excluded: bool) -> usize {
+        let Ok(dir_entries) = fs::read_dir(path) else {
+            return 0;
+        };
+        dir_entries
+            .flatten()
+            .map(|entry| FileItem::new(&entry.path(), 0, false, self.follow_links))
+            .filter(|item| self.verdict(item, inside_excluded).listed)
+            .count()
+    }
+
+    /// 统计 `-L` 截断的目录下所有会被列出的条目，与列出时一样去掉其中没有列出条目的目录
+    fn count_entries_below(&mut self, path: &Path, inside_excluded: bool) -> EntryCount {
+        let mut count = EntryCount::default();
+        let Ok(dir_entries) = fs::read_dir(path) else {
+            return count;
+        };
+        for entry in dir_entries.flatten() {
+            let mut item = FileItem::new(&entry.path(), 0, false, self.follow_links);
+            if !self.is_included(&mut item, inside_excluded) {
+                continue;
+            }
+            if !item.is_dir() {
+                count.files += 1;
+                continue;
+            }
+            let below = if self.mark_visited(&item) {
+                self.count_entries_below(&item.path(), item.excluded)
+            } else {
+                EntryCount::default()
+            };
+            let listed = if self.include_glob.is_some() {
+                below.files > 0
+            } else {
+                !item.container || below.files + below.dirs > 0
+            };
+            if listed {
+                count.files += below.files;
+                count.dirs += below.dirs + 1;
+            }
+        }
+        count
     }
 }
 
@@ -109,9 +597,22 @@ impl Iterator for FileIterator {
     type Item = FileItem;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(item) = self.queue.pop_back() {
+        if let Some(mut item) = self.queue.pop_back() {
             if item.is_dir() && item.level < self.max_level {
-                self.push_dir(&item);
+                if self.mark_visited(&item) {
+                    if let Err(err) = self.push_dir(&item) {
+                        item.dir_error = Some(err);
+                    }
+                } else {
+                    item.recursive = true;
+                }
+            } else if item.is_dir() {
+                if self.show_truncated {
+                    item.not_shown = Some(self.count_entries_at(&item.path(), item.excluded));
+                }
+                if self.count_below && self.mark_visited(&item) {
+                    item.below_cutoff = Some(self.count_entries_below(&item.path(), item.excluded));
+                }
             }
             Some(item)
         } else {
@@ -119,3 +620,195 @@ impl Iterator for FileIterator {
         }
     }
 }
+
+/// 没有文件名的根路径（`/`、`C:\`、`\\server\share\`）显示为路径本身，去掉 `\\?\` 前缀
+pub fn root_name(path: &Path) -> OsString {
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.as_os_str().to_owned();
+    };
+    let mut name = match prefix.kind() {
+        Prefix::Disk(disk) | Prefix::VerbatimDisk(disk) => {
+            OsString::from(format!("{}:", disk as char))
+        }
+        Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+            let mut name = OsString::from(r"\\");
+            name.push(server);
+            name.push(r"\");
+            name.push(share);
+            name
+        }
+        _ => prefix.as_os_str().to_owned(),
+    };
+    // 前缀之后剩下的部分，通常只有根目录分隔符
+    name.push(components.as_path());
+    name
+}
+
+/// 少于这个数目的目录不值得启动线程
+const PARALLEL_STAT_MIN: usize = 64;
+/// 默认同时读取元数据的线程数上限，更多线程对单块磁盘没有帮助
+const MAX_STAT_THREADS: usize = 8;
+
+/// The number of threads that stat entries by default: one per logical CPU, at most 8.
+pub fn default_threads() -> usize {
+    thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_STAT_THREADS)
+}
+
+/// Stat `paths` on up to `threads` threads and return the items in the original order, with the
+/// number of threads used. Network filesystems spend most of a listing waiting on one round
+/// trip per entry.
+fn stat_parallel(
+    dir: &Arc<Path>,
+    paths: &[PathBuf],
+    level: usize,
+    follow_links: bool,
+    threads: usize,
+) -> (Vec<FileItem>, usize) {
+    if paths.len() < PARALLEL_STAT_MIN || threads < 2 {
+        let items = paths
+            .iter()
+            .map(|path| FileItem::in_dir(dir, path, level, follow_links))
+            .collect();
+        return (items, 1);
+    }
+    let chunk_size = paths.len().div_ceil(threads);
+    let items = std::thread::scope(|scope| {
+        let workers: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| FileItem::in_dir(dir, path, level, follow_links))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+    (items, paths.len().div_ceil(chunk_size))
+}
+
+/// 在工作线程中执行 `op`，超过 `timeout` 就不再等待。卡在内核里的线程无法取消，只能放任它
+/// 在后台结束
+fn run_with_timeout<T: Send + 'static>(
+    timeout: Duration,
+    op: impl FnOnce() -> T + Send + 'static,
+) -> io::Result<T> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(op());
+    });
+    receiver
+        .recv_timeout(timeout)
+        .map_err(|_| io::ErrorKind::TimedOut.into())
+}
+
+/// Stat `paths` on up to `threads` worker threads like [`stat_parallel`], but give each worker
+/// at most `timeout` per entry. Once an entry times out that worker is assumed to be stuck and
+/// the rest of its share is marked too, so a wedged mount costs one timeout per worker rather
+/// than one per entry. Returns the items in the original order and the number of workers.
+fn stat_with_timeout(
+    dir: &Arc<Path>,
+    paths: &[PathBuf],
+    level: usize,
+    follow_links: bool,
+    timeout: Duration,
+    threads: usize,
+) -> (Vec<FileItem>, usize) {
+    let workers = if paths.len() < PARALLEL_STAT_MIN {
+        1
+    } else {
+        threads.max(1)
+    };
+    let chunk_size = paths.len().div_ceil(workers).max(1);
+    // 卡住的线程无法取消，不能用 `thread::scope` 等它们结束
+    let receivers: Vec<_> = paths
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let (sender, receiver) = mpsc::channel();
+            let chunk = chunk.to_vec();
+            let dir = Arc::clone(dir);
+            thread::spawn(move || {
+                for path in chunk {
+                    if sender
+                        .send(FileItem::in_dir(&dir, &path, level, follow_links))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+            receiver
+        })
+        .collect();
+    let spawned = receivers.len();
+    let mut items = Vec::with_capacity(paths.len());
+    for (chunk, receiver) in paths.chunks(chunk_size).zip(receivers) {
+        let start = items.len();
+        while items.len() - start < chunk.len() {
+            match receiver.recv_timeout(timeout) {
+                Ok(item) => items.push(item),
+                Err(_) => break,
+            }
+        }
+        let done = items.len() - start;
+        items.extend(
+            chunk[done..]
+                .iter()
+                .map(|path| FileItem::timed_out(dir, path, level)),
+        );
+    }
+    (items, spawned)
+}
+
+/// 目录中没有任何条目（包括隐藏文件）
+pub fn is_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+}
+
+/// A name or path as text, with bytes that are not valid UTF-8 written as `\xNN` so that
+/// different names never print the same.
+pub fn escape_name(name: &OsStr) -> Cow<'_, str> {
+    if let Some(name) = name.to_str() {
+        return Cow::Borrowed(name);
+    }
+    let mut escaped = String::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        escaped.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            let _ = write!(escaped, "\\x{:02X}", byte);
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// 文件所在设备及 inode 编号
+#[cfg(unix)]
+pub fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn file_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// 文件存在多个硬链接
+#[cfg(unix)]
+pub fn has_hard_links(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+#[cfg(not(unix))]
+pub fn has_hard_links(_metadata: &Metadata) -> bool {
+    false
+}
@@ -1,28 +1,52 @@
 use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use globset::{GlobSet, GlobSetBuilder};
 
 use crate::file_iterator::{FileItem, FileIterator};
+use crate::pattern::Pattern;
 
+/// Drops the directories that should only be listed when something inside them is: with `-P`
+/// every directory without a matching file, and directories excluded by `-E` (or not hidden
+/// with `--hidden-only`) that were read only because entries in them may be listed.
 pub struct FilteredIterator {
     current: FileIterator,
+    /// 还不知道是否要列出的目录，从外到内
     cache: VecDeque<FileItem>,
+    /// `-P` 时目录要等到其中出现列出的文件才列出
+    dirs_need_files: bool,
     skip: bool,
     next_item: Option<FileItem>,
 }
 
 impl FilteredIterator {
-    pub fn new(iterator: FileIterator) -> Self {
+    pub fn new(iterator: FileIterator, dirs_need_files: bool) -> Self {
         FilteredIterator {
             current: iterator,
             cache: VecDeque::new(),
+            dirs_need_files,
             skip: false,
             next_item: None,
         }
     }
 
+    /// 不再去掉没有列出条目的目录；已经缓存的条目仍会依次返回
     pub fn skip_filter(&mut self) {
         self.skip = true;
     }
 
+    pub fn inner(&self) -> &FileIterator {
+        &self.current
+    }
+
+    /// 目录要等到其中出现列出的条目才列出；被 `-L` 截断的目录按统计的条目数决定
+    fn is_pending(&self, item: &FileItem) -> bool {
+        let below_cutoff = item.below_cutoff.is_some_and(|c| c.files + c.dirs > 0)
+            || item.not_shown.is_some_and(|n| n > 0);
+        item.is_dir() && (self.dirs_need_files || item.container) && !below_cutoff
+    }
+
     fn remove_empty_directories_from_cache(&mut self, item: &FileItem) {
         while let Some(last) = self.cache.pop_back() {
             if last.level < item.level {
@@ -37,19 +61,19 @@ impl Iterator for FilteredIterator {
     type Item = FileItem;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.skip {
-            return self.current.next();
-        }
         if let Some(cache_item) = self.cache.pop_front() {
             return Some(cache_item);
         }
         if let Some(next_item) = self.next_item.take() {
             return Some(next_item);
         }
+        if self.skip {
+            return self.current.next();
+        }
         while let Some(item) = self.current.next() {
             self.remove_empty_directories_from_cache(&item);
 
-            if item.is_dir() {
+            if self.is_pending(&item) {
                 self.cache.push_back(item)
             } else {
                 return if let Some(cache_front) = self.cache.pop_front() {
@@ -63,3 +87,94 @@ impl Iterator for FilteredIterator {
         None
     }
 }
+
+/// `-E` rules evaluated in order with the last matching rule deciding, like `.gitignore`: a
+/// rule starting with `!` brings back entries an earlier rule excluded.
+///
+/// The rules are compiled into one set for names and one for paths, so an entry is checked
+/// against all of them in two passes however many rules there are.
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeRules {
+    rules: Vec<Pattern>,
+    names: GlobSet,
+    paths: GlobSet,
+    /// 两个集合中第 n 个 glob 对应的规则下标
+    name_rules: Vec<usize>,
+    path_rules: Vec<usize>,
+}
+
+impl ExcludeRules {
+    pub fn new(rules: Vec<Pattern>) -> ExcludeRules {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        let mut name_rules = Vec::new();
+        let mut path_rules = Vec::new();
+        for (index, rule) in rules.iter().enumerate() {
+            if rule.matches_path() {
+                paths.add(rule.glob().clone());
+                path_rules.push(index);
+            } else {
+                names.add(rule.glob().clone());
+                name_rules.push(index);
+            }
+        }
+        ExcludeRules {
+            // 每个 glob 单独都能编译，合在一起也能编译
+            names: names.build().expect("rules are valid globs"),
+            paths: paths.build().expect("rules are valid globs"),
+            rules,
+            name_rules,
+            path_rules,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 是否有含 `/` 的规则，需要条目相对根目录的路径
+    pub fn matches_paths(&self) -> bool {
+        !self.path_rules.is_empty()
+    }
+
+    /// Return whether the entry is excluded and the rule that decided. `inside_excluded` is the
+    /// state inherited from the parent directory, which applies when no rule matches.
+    pub fn decide(
+        &self,
+        name: &OsStr,
+        relative: &Path,
+        is_dir: bool,
+        inside_excluded: bool,
+    ) -> (bool, Option<&Pattern>) {
+        if self.rules.is_empty() {
+            return (inside_excluded, None);
+        }
+        let by_name = self
+            .names
+            .matches(name)
+            .into_iter()
+            .map(|i| self.name_rules[i]);
+        let by_path = self
+            .paths
+            .matches(relative)
+            .into_iter()
+            .map(|i| self.path_rules[i]);
+        let matching = by_name
+            .chain(by_path)
+            .filter(|&i| is_dir || !self.rules[i].is_dir_only());
+        match matching.max() {
+            Some(index) => {
+                let rule = &self.rules[index];
+                (!rule.is_negated(), Some(rule))
+            }
+            None => (inside_excluded, None),
+        }
+    }
+
+    /// 被排除的目录中是否可能有被 `!` 重新包含的条目，有的话仍需读取它
+    pub fn may_reinclude_below(&self, dir: &Path) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.is_negated() && rule.may_match_below(dir))
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs::Metadata;
+
+/// `<sys/stat.h>` 中的文件标志位及其在 `ls -lO` 中的名字
+pub const UF_NODUMP: u32 = 0x0000_0001;
+pub const UF_IMMUTABLE: u32 = 0x0000_0002;
+pub const UF_APPEND: u32 = 0x0000_0004;
+pub const UF_OPAQUE: u32 = 0x0000_0008;
+pub const UF_HIDDEN: u32 = 0x0000_8000;
+pub const SF_ARCHIVED: u32 = 0x0001_0000;
+pub const SF_IMMUTABLE: u32 = 0x0002_0000;
+pub const SF_APPEND: u32 = 0x0004_0000;
+
+const FLAG_NAMES: [(u32, &str); 8] = [
+    (UF_NODUMP, "nodump"),
+    (UF_IMMUTABLE, "uchg"),
+    (UF_APPEND, "uappnd"),
+    (UF_OPAQUE, "opaque"),
+    (UF_HIDDEN, "hidden"),
+    (SF_ARCHIVED, "arch"),
+    (SF_IMMUTABLE, "schg"),
+    (SF_APPEND, "sappnd"),
+];
+
+/// Whether this platform has BSD file flags (`st_flags`).
+pub const SUPPORTED: bool = cfg!(any(target_os = "macos", target_os = "freebsd"));
+
+#[cfg(target_os = "macos")]
+pub fn file_flags(metadata: &Metadata) -> Option<u32> {
+    use std::os::macos::fs::MetadataExt;
+    Some(metadata.st_flags())
+}
+
+#[cfg(target_os = "freebsd")]
+pub fn file_flags(metadata: &Metadata) -> Option<u32> {
+    use std::os::freebsd::fs::MetadataExt;
+    Some(metadata.st_flags())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "freebsd")))]
+pub fn file_flags(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+/// Names of the set flags joined by commas like `ls -lO`, or `-` when none is set. Bits
+/// without a known name are shown in hex.
+pub fn flag_names(flags: u32) -> String {
+    let mut names: Vec<String> = FLAG_NAMES
+        .iter()
+        .filter(|(bit, _)| flags & bit != 0)
+        .map(|(_, name)| name.to_string())
+        .collect();
+    let known = FLAG_NAMES.iter().fold(0, |all, (bit, _)| all | bit);
+    if flags & !known != 0 {
+        names.push(format!("{:#x}", flags & !known));
+    }
+    if names.is_empty() {
+        "-".to_string()
+    } else {
+        names.join(",")
+    }
+}
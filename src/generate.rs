@@ -0,0 +1,115 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 目录名使用的单词
+const DIR_WORDS: [&str; 10] = [
+    "src", "docs", "assets", "lib", "tests", "config", "data", "build", "images", "scripts",
+];
+/// 文件名使用的单词和扩展名
+const FILE_WORDS: [&str; 10] = [
+    "main", "index", "report", "notes", "util", "schema", "readme", "logo", "query", "backup",
+];
+const EXTENSIONS: [&str; 10] = [
+    "rs", "md", "txt", "json", "toml", "png", "sql", "js", "csv", "log",
+];
+/// 文件内容最大的字节数
+const MAX_FILE_SIZE: usize = 4096;
+/// 一个目录下最多的子目录数，避免树退化成一层
+const MAX_SUBDIRS: usize = 6;
+
+/// How the synthetic tree of `tree-cli generate` is shaped.
+#[derive(Debug, Clone, Copy)]
+pub struct GenerateOptions {
+    /// 文件所在的最大层级，根目录为 0
+    pub depth: usize,
+    pub files: usize,
+    pub seed: u64,
+}
+
+/// What was created, not counting the root directory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Generated {
+    pub dirs: usize,
+    pub files: usize,
+}
+
+/// SplitMix64，同一个种子在所有平台上生成同样的序列
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// `0..n` 中的一个数
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, words: &[&'a str]) -> &'a str {
+        words[self.below(words.len())]
+    }
+}
+
+struct Dir {
+    path: PathBuf,
+    level: usize,
+    children: Vec<usize>,
+}
+
+/// Create a synthetic tree under `root`, which must not exist yet or be empty. The same options
+/// always produce the same names, nesting and file sizes, so a tree can be recreated from the
+/// command line that made it.
+pub fn generate(root: &Path, options: &GenerateOptions) -> io::Result<Generated> {
+    fs::create_dir_all(root)?;
+    let mut rng = Rng(options.seed);
+    let mut dirs = vec![Dir {
+        path: root.to_path_buf(),
+        level: 0,
+        children: Vec::new(),
+    }];
+    let mut generated = Generated::default();
+    for index in 0..options.files {
+        // 从根目录往下走到随机的层级，途中按需创建新目录
+        let level = rng.below(options.depth + 1);
+        let mut current = 0;
+        while dirs[current].level < level {
+            let children = &dirs[current].children;
+            let create = children.is_empty() || (children.len() < MAX_SUBDIRS && rng.below(3) == 0);
+            current = if create {
+                let name = format!("{}_{:02}", rng.pick(&DIR_WORDS), dirs.len());
+                let path = dirs[current].path.join(name);
+                fs::create_dir(&path)?;
+                generated.dirs += 1;
+                dirs.push(Dir {
+                    path,
+                    level: dirs[current].level + 1,
+                    children: Vec::new(),
+                });
+                let child = dirs.len() - 1;
+                dirs[current].children.push(child);
+                child
+            } else {
+                children[rng.below(children.len())]
+            };
+        }
+        // 大约十分之一的文件是隐藏文件
+        let hidden = if rng.below(10) == 0 { "." } else { "" };
+        let name = format!(
+            "{}{}_{:03}.{}",
+            hidden,
+            rng.pick(&FILE_WORDS),
+            index,
+            rng.pick(&EXTENSIONS)
+        );
+        let size = rng.below(MAX_FILE_SIZE + 1);
+        fs::write(dirs[current].path.join(name), vec![b'x'; size])?;
+        generated.files += 1;
+    }
+    Ok(generated)
+}
@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// `git ls-files` 列出的文件以及包含它们的目录
+#[derive(Debug, Clone, Default)]
+pub struct TrackedFiles {
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl TrackedFiles {
+    /// 合并另一个仓库（或另一个根目录）的结果
+    pub fn extend(&mut self, other: TrackedFiles) {
+        self.files.extend(other.files);
+        self.dirs.extend(other.dirs);
+    }
+
+    /// 子模块在 `ls-files` 中以目录的形式出现，所以目录也要检查 `files`
+    pub fn contains(&self, path: &Path, is_dir: bool) -> bool {
+        self.files.contains(path) || (is_dir && self.dirs.contains(path))
+    }
+}
+
+/// Ask git which files under `root` are tracked. Paths are joined onto `root` so they compare
+/// equal to the paths produced while walking it.
+pub fn tracked_files(root: &Path) -> io::Result<TrackedFiles> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["ls-files", "-z"])
+        .output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(message));
+    }
+
+    let mut tracked = TrackedFiles::default();
+    for name in output
+        .stdout
+        .split(|b| *b == 0)
+        .filter(|name| !name.is_empty())
+    {
+        let file = root.join(bytes_to_path(name));
+        for dir in file.ancestors().skip(1) {
+            if dir == root || !tracked.dirs.insert(dir.to_path_buf()) {
+                break;
+            }
+        }
+        tracked.files.insert(file);
+    }
+    Ok(tracked)
+}
+
+/// 仓库根目录的当前分支和工作区是否有改动
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoStatus {
+    /// 分离头指针时为 `HEAD`
+    pub branch: String,
+    pub dirty: bool,
+}
+
+/// 目录中有 `.git` 时是仓库的根目录；子模块和额外的工作树中 `.git` 是一个文件
+pub fn is_repo_root(dir: &Path) -> bool {
+    dir.join(".git").symlink_metadata().is_ok()
+}
+
+/// Ask git for the current branch of the repository rooted at `dir` and whether it has
+/// uncommitted changes, untracked files included.
+pub fn repo_status(dir: &Path) -> io::Result<RepoStatus> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["status", "--porcelain", "--branch"])
+        .output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(message));
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut lines = text.lines();
+    let header = lines.next().unwrap_or_default();
+    Ok(RepoStatus {
+        branch: branch_name(header),
+        dirty: lines.next().is_some(),
+    })
+}
+
+/// 解析 `## main...origin/main [ahead 1]`、`## No commits yet on main` 和
+/// `## HEAD (no branch)` 这样的第一行
+fn branch_name(header: &str) -> String {
+    let header = header.trim_start_matches("## ");
+    let header = header
+        .strip_prefix("No commits yet on ")
+        .or_else(|| header.strip_prefix("Initial commit on "))
+        .unwrap_or(header);
+    let name = header.split("...").next().unwrap_or(header);
+    let name = name.split(' ').next().unwrap_or(name);
+    name.to_string()
+}
+
+#[cfg(unix)]
+fn bytes_to_path(bytes: &[u8]) -> &Path {
+    use std::os::unix::ffi::OsStrExt;
+    Path::new(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(bytes: &[u8]) -> &Path {
+    // git 在其他平台上输出 UTF-8 路径
+    Path::new(OsStr::new(std::str::from_utf8(bytes).unwrap_or_default()))
+}
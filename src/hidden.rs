@@ -0,0 +1,36 @@
+use std::ffi::OsStr;
+use std::fs::Metadata;
+
+/// Whether an entry counts as hidden on this platform: a leading dot on Unix, the hidden
+/// attribute on Windows, and either of them or the `UF_HIDDEN` flag on macOS (what Finder hides).
+/// `metadata` is `None` when it could not be read; only the name is checked then.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn is_hidden(name: &OsStr, _metadata: Option<&Metadata>) -> bool {
+    is_dot_file(name)
+}
+
+#[cfg(target_os = "macos")]
+pub fn is_hidden(name: &OsStr, metadata: Option<&Metadata>) -> bool {
+    use crate::flags::{file_flags, UF_HIDDEN};
+    is_dot_file(name)
+        || metadata
+            .and_then(file_flags)
+            .is_some_and(|f| f & UF_HIDDEN != 0)
+}
+
+#[cfg(windows)]
+pub fn is_hidden(_name: &OsStr, metadata: Option<&Metadata>) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    use windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_HIDDEN;
+    metadata.is_some_and(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn is_hidden(name: &OsStr, _metadata: Option<&Metadata>) -> bool {
+    is_dot_file(name)
+}
+
+#[cfg(not(windows))]
+fn is_dot_file(name: &OsStr) -> bool {
+    name.as_encoded_bytes().starts_with(b".")
+}
@@ -0,0 +1,228 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::core::{Node, Tree};
+use crate::file_iterator::FileItem;
+use crate::symbol::{set_line_prefix, Charset};
+
+/// 与 GNU tree 相同的默认页面标题
+pub const DEFAULT_TITLE: &str = "Directory Tree";
+
+#[derive(Clone)]
+pub struct HtmlOptions {
+    /// 链接的前缀，对应 `-H <baseHREF>`
+    pub base_href: String,
+    pub title: String,
+    /// 是否为每个条目生成超链接
+    pub links: bool,
+    /// 替换默认页面开头（`<html>` 到 `<h1>`）的片段
+    pub intro: Option<String>,
+    /// 替换默认页面结尾（`</body></html>`）的片段
+    pub outro: Option<String>,
+    /// 目录可以折叠，并提供一个过滤框
+    pub interactive: bool,
+    /// 每一层缩进的宽度
+    pub indent: usize,
+    /// 超过这个深度的外层前缀合并成 `…`
+    pub max_prefix_depth: Option<usize>,
+}
+
+/// `--html-interactive` 的样式和脚本，放在树的后面，替换开头或结尾时也会生效
+const INTERACTIVE_ASSETS: &str = r#"<style>
+.tree .line { white-space: pre; }
+.tree .toggle { cursor: pointer; user-select: none; }
+.tree .hidden { display: none; }
+#tree-filter { margin-bottom: 0.5em; }
+</style>
+<script>
+(function () {
+  var lines = Array.prototype.slice.call(document.querySelectorAll('.tree .line'));
+  var filter = document.getElementById('tree-filter');
+  function depth(line) { return Number(line.getAttribute('data-depth')); }
+  function refresh() {
+    var query = filter.value.toLowerCase();
+    var visible = [];
+    if (query) {
+      // 显示匹配的条目以及它们的上级目录
+      var matchedBelow = [];
+      for (var i = lines.length - 1; i >= 0; i--) {
+        var d = depth(lines[i]);
+        var name = lines[i].querySelector('.name').textContent.toLowerCase();
+        visible[i] = name.indexOf(query) >= 0 || !!matchedBelow[d];
+        matchedBelow.length = d;
+        if (visible[i]) {
+          for (var k = 0; k < d; k++) { matchedBelow[k] = true; }
+        }
+      }
+    } else {
+      var cut = Infinity;
+      for (var j = 0; j < lines.length; j++) {
+        var depthJ = depth(lines[j]);
+        if (depthJ <= cut) { cut = Infinity; }
+        visible[j] = cut === Infinity;
+        if (visible[j] && lines[j].classList.contains('collapsed')) { cut = depthJ; }
+      }
+    }
+    lines.forEach(function (line, index) { line.classList.toggle('hidden', !visible[index]); });
+  }
+  document.querySelectorAll('.tree .toggle').forEach(function (toggle) {
+    toggle.addEventListener('click', function () {
+      var collapsed = toggle.parentNode.classList.toggle('collapsed');
+      toggle.textContent = collapsed ? '\u25b8 ' : '\u25be ';
+      refresh();
+    });
+  });
+  filter.addEventListener('input', refresh);
+})();
+</script>
+"#;
+
+/// Render the tree as an HTML page whose entries link below `base_href`.
+pub fn render_html(tree: &Tree, options: &HtmlOptions, report: Option<&str>) -> String {
+    let mut page = String::new();
+    match options.intro {
+        Some(ref intro) => page.push_str(intro),
+        None => {
+            let title = escape_html(&options.title);
+            let _ = write!(
+                page,
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+                 <style>\n.tree {{ line-height: 1.2; }}\n.dir {{ font-weight: bold; }}\n</style>\n\
+                 </head>\n<body>\n<h1>{}</h1>\n",
+                title, title
+            );
+        }
+    }
+
+    if options.interactive {
+        page.push_str("<input id=\"tree-filter\" type=\"search\" placeholder=\"Filter\">\n");
+    }
+    page.push_str("<pre class=\"tree\">\n");
+    let mut symbol_switch_list: Vec<bool> = Vec::new();
+    let mut prefix = String::new();
+    let root = &tree.root.item.path();
+    html_node(
+        &tree.root,
+        root,
+        options,
+        &mut symbol_switch_list,
+        &mut prefix,
+        &mut page,
+    );
+    page.push_str("</pre>\n");
+    if options.interactive {
+        page.push_str(INTERACTIVE_ASSETS);
+    }
+    if let Some(report) = report {
+        let _ = writeln!(
+            page,
+            "<hr>\n<p class=\"report\">{}</p>",
+            escape_html(report)
+        );
+    }
+
+    match options.outro {
+        Some(ref outro) => page.push_str(outro),
+        None => page.push_str("</body>\n</html>\n"),
+    }
+    page
+}
+
+fn html_node(
+    node: &Node,
+    root: &Path,
+    options: &HtmlOptions,
+    symbol_switch_list: &mut Vec<bool>,
+    prefix: &mut String,
+    page: &mut String,
+) {
+    set_line_prefix(
+        symbol_switch_list,
+        prefix,
+        Charset::Utf8,
+        options.indent,
+        options.max_prefix_depth,
+    );
+    if options.interactive {
+        let _ = write!(
+            page,
+            "<div class=\"line\" data-depth=\"{}\">{}",
+            symbol_switch_list.len(),
+            prefix
+        );
+        if node.item.is_dir() {
+            page.push_str("<span class=\"toggle\">\u{25be} </span>");
+        }
+        html_entry(&node.item, root, options, page);
+        page.push_str("</div>");
+    } else {
+        page.push_str(prefix);
+        html_entry(&node.item, root, options, page);
+        page.push('\n');
+    }
+
+    let count = node.children.len();
+    for (index, child) in node.children.iter().enumerate() {
+        symbol_switch_list.push(index + 1 < count);
+        html_node(child, root, options, symbol_switch_list, prefix, page);
+        symbol_switch_list.pop();
+    }
+}
+
+fn html_entry(item: &FileItem, root: &Path, options: &HtmlOptions, page: &mut String) {
+    let class = if item.is_dir() {
+        "name dir"
+    } else {
+        "name file"
+    };
+    let name = escape_html(&item.display_name());
+    if !options.links {
+        let _ = write!(page, "<span class=\"{}\">{}</span>", class, name);
+        return;
+    }
+    let mut href = options.base_href.trim_end_matches('/').to_string();
+    if let Ok(relative) = item.path().strip_prefix(root) {
+        for component in relative.iter() {
+            href.push('/');
+            href.push_str(&percent_encode(component.as_encoded_bytes()));
+        }
+    }
+    if item.is_dir() {
+        href.push('/');
+    }
+    let _ = write!(
+        page,
+        "<a class=\"{}\" href=\"{}\">{}</a>",
+        class,
+        escape_html(&href),
+        name
+    );
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 对 URL 路径中的一段进行百分号编码
+fn percent_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+    }
+    encoded
+}
@@ -0,0 +1,658 @@
+use std::env;
+use std::fmt::Display;
+use std::path::Path;
+
+use clap::ValueEnum;
+use unicode_width::UnicodeWidthStr;
+
+use crate::bench::{BenchReport, TimingReport};
+use crate::core::{DirSummary, KindCounts};
+use crate::file_iterator::{Rule, Verdict};
+use crate::size::human_size;
+use crate::symbol::SpecialKind;
+
+/// 用户可见文本的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// 简体中文
+    Zh,
+}
+
+impl Lang {
+    /// Pick the language from `LC_ALL`, `LC_MESSAGES` or `LANG`, defaulting to English.
+    pub fn from_env() -> Lang {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|key| env::var(key).ok())
+            .find(|value| !value.is_empty())
+            .map(|value| Lang::from_locale(&value))
+            .unwrap_or(Lang::En)
+    }
+
+    fn from_locale(locale: &str) -> Lang {
+        if locale.to_ascii_lowercase().starts_with("zh") {
+            Lang::Zh
+        } else {
+            Lang::En
+        }
+    }
+
+    /// `X directories, Y files`，有符号链接时再加上 `, Z symlinks`
+    pub fn report(&self, num_folders: usize, num_files: usize, num_symlinks: usize) -> String {
+        let mut report = match self {
+            Lang::En => format!("{} directories, {} files", num_folders, num_files),
+            Lang::Zh => format!("{} 个目录，{} 个文件", num_folders, num_files),
+        };
+        if num_symlinks > 0 {
+            report.push_str(&match self {
+                Lang::En => format!(", {} symlinks", num_symlinks),
+                Lang::Zh => format!("，{} 个符号链接", num_symlinks),
+            });
+        }
+        report
+    }
+
+    pub fn report_with_size(&self, counts: String, size: &str) -> String {
+        match self {
+            Lang::En => format!("{}, {} bytes used", counts, size),
+            Lang::Zh => format!("{}，共占用 {} 字节", counts, size),
+        }
+    }
+
+    pub fn report_with_total(&self, counts: String, total: &str) -> String {
+        match self {
+            Lang::En => format!("{}, {} total", counts, total),
+            Lang::Zh => format!("{}，共 {}", counts, total),
+        }
+    }
+
+    pub fn report_types(&self, kinds: &KindCounts) -> String {
+        match self {
+            Lang::En => format!(
+                "{} regular files ({} executable), {} directories, {} symlinks, {} fifos, {} sockets, {} devices",
+                kinds.regular,
+                kinds.executables,
+                kinds.directories,
+                kinds.symlinks,
+                kinds.fifos,
+                kinds.sockets,
+                kinds.devices
+            ),
+            Lang::Zh => format!(
+                "{} 个普通文件（{} 个可执行），{} 个目录，{} 个符号链接，{} 个命名管道，{} 个套接字，{} 个设备",
+                kinds.regular,
+                kinds.executables,
+                kinds.directories,
+                kinds.symlinks,
+                kinds.fifos,
+                kinds.sockets,
+                kinds.devices
+            ),
+        }
+    }
+
+    pub fn inodes_used(&self, inodes: u64) -> String {
+        match self {
+            Lang::En => format!(", {} inodes", inodes),
+            Lang::Zh => format!("，{} 个 inode", inodes),
+        }
+    }
+
+    /// `--disk-usage=both` 时附在报告后面的占用空间
+    pub fn allocated_total(&self, allocated: &str) -> String {
+        match self {
+            Lang::En => format!(" ({} allocated)", allocated),
+            Lang::Zh => format!("（占用磁盘 {}）", allocated),
+        }
+    }
+
+    pub fn report_details(&self, summary: &DirSummary) -> String {
+        let seconds = summary.elapsed.as_secs_f64();
+        match self {
+            Lang::En => format!(
+                "{} bytes, {} symlinks, {} hidden, {} errors, {:.3}s elapsed",
+                summary.total_size,
+                summary.num_symlinks,
+                summary.num_hidden,
+                summary.num_errors,
+                seconds
+            ),
+            Lang::Zh => format!(
+                "{} 字节，{} 个符号链接，{} 个隐藏条目，{} 个错误，耗时 {:.3} 秒",
+                summary.total_size,
+                summary.num_symlinks,
+                summary.num_hidden,
+                summary.num_errors,
+                seconds
+            ),
+        }
+    }
+
+    pub fn error_file(&self) -> &'static str {
+        match self {
+            Lang::En => "[Error File]",
+            Lang::Zh => "[错误文件]",
+        }
+    }
+
+    pub fn zero_byte(&self) -> &'static str {
+        match self {
+            Lang::En => "[empty]",
+            Lang::Zh => "[空文件]",
+        }
+    }
+
+    pub fn sparse(&self) -> &'static str {
+        match self {
+            Lang::En => "[sparse]",
+            Lang::Zh => "[稀疏文件]",
+        }
+    }
+
+    pub fn below_cutoff(&self, num_files: usize, num_dirs: usize) -> String {
+        match self {
+            Lang::En => format!("… ({} files, {} dirs)", num_files, num_dirs),
+            Lang::Zh => format!("… ({} 个文件，{} 个目录)", num_files, num_dirs),
+        }
+    }
+
+    pub fn empty_dir(&self) -> &'static str {
+        match self {
+            Lang::En => "(empty)",
+            Lang::Zh => "(空)",
+        }
+    }
+
+    pub fn recursive_not_followed(&self) -> &'static str {
+        match self {
+            Lang::En => "[recursive, not followed]",
+            Lang::Zh => "[循环链接，未跟随]",
+        }
+    }
+
+    pub fn timed_out(&self) -> &'static str {
+        match self {
+            Lang::En => "[timed out]",
+            Lang::Zh => "[超时]",
+        }
+    }
+
+    pub fn error_opening_dir(&self) -> &'static str {
+        match self {
+            Lang::En => "[error opening dir]",
+            Lang::Zh => "[无法打开目录]",
+        }
+    }
+
+    pub fn invalid_pattern(&self, pattern: &str, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("invalid pattern '{}': {}", pattern, err),
+            Lang::Zh => format!("无效的匹配模式 '{}': {}", pattern, err),
+        }
+    }
+
+    pub fn invalid_level(&self) -> &'static str {
+        match self {
+            Lang::En => "Invalid level, must be greater than 0.",
+            Lang::Zh => "无效的层级，必须大于 0。",
+        }
+    }
+
+    pub fn invalid_depth_range(&self, min_depth: usize, max_level: usize) -> String {
+        match self {
+            Lang::En => format!("--min-depth {} is deeper than -L {}", min_depth, max_level),
+            Lang::Zh => format!("--min-depth {} 比 -L {} 更深", min_depth, max_level),
+        }
+    }
+
+    pub fn invalid_time_format(&self, format: &str) -> String {
+        match self {
+            Lang::En => format!("invalid time format '{}'", format),
+            Lang::Zh => format!("无效的时间格式 '{}'", format),
+        }
+    }
+
+    pub fn root_unreadable(&self, path: &Path, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("{}: {}", path.display(), err),
+            Lang::Zh => format!("{}: 无法访问 ({})", path.display(), err),
+        }
+    }
+
+    pub fn execution_failure(&self, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("execution failure: {}", err),
+            Lang::Zh => format!("执行失败: {}", err),
+        }
+    }
+
+    pub fn clipboard_failed(&self, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("Could not copy to clipboard: {}", err),
+            Lang::Zh => format!("无法复制到剪贴板: {}", err),
+        }
+    }
+
+    pub fn verify_changed(&self) -> &'static str {
+        match self {
+            Lang::En => "[changed]",
+            Lang::Zh => "[已变更]",
+        }
+    }
+
+    pub fn verify_added(&self) -> &'static str {
+        match self {
+            Lang::En => "[not in manifest]",
+            Lang::Zh => "[清单中没有]",
+        }
+    }
+
+    pub fn verify_missing(&self) -> &'static str {
+        match self {
+            Lang::En => "[missing]",
+            Lang::Zh => "[缺失]",
+        }
+    }
+
+    pub fn compare_report(&self, added: usize, removed: usize, modified: usize) -> String {
+        match self {
+            Lang::En => format!(
+                "{} added, {} removed, {} modified",
+                added, removed, modified
+            ),
+            Lang::Zh => format!("新增 {}，删除 {}，修改 {}", added, removed, modified),
+        }
+    }
+
+    pub fn archive_unreadable(&self, archive: &Path, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("could not read archive {}: {}", archive.display(), err),
+            Lang::Zh => format!("无法读取归档 {}: {}", archive.display(), err),
+        }
+    }
+
+    pub fn archive_differs(&self, dir: &Path, archive: &Path) -> String {
+        match self {
+            Lang::En => format!("{} differs from {}", dir.display(), archive.display()),
+            Lang::Zh => format!("{} 与 {} 不一致", dir.display(), archive.display()),
+        }
+    }
+
+    pub fn generate_target_not_empty(&self, path: &Path) -> String {
+        match self {
+            Lang::En => format!("{}: directory is not empty", path.display()),
+            Lang::Zh => format!("{}: 目录不是空的", path.display()),
+        }
+    }
+
+    pub fn generated(&self, path: &Path, dirs: usize, files: usize) -> String {
+        match self {
+            Lang::En => format!(
+                "created {} directories, {} files in {}",
+                dirs,
+                files,
+                path.display()
+            ),
+            Lang::Zh => format!(
+                "已在 {} 中创建 {} 个目录，{} 个文件",
+                path.display(),
+                dirs,
+                files
+            ),
+        }
+    }
+
+    pub fn pinned(&self, path: &Path) -> String {
+        match self {
+            Lang::En => format!("pinned {}", path.display()),
+            Lang::Zh => format!("已收藏 {}", path.display()),
+        }
+    }
+
+    pub fn unpinned(&self, path: &Path) -> String {
+        match self {
+            Lang::En => format!("removed the pin of {}", path.display()),
+            Lang::Zh => format!("已取消收藏 {}", path.display()),
+        }
+    }
+
+    pub fn not_pinned(&self, path: &Path) -> String {
+        match self {
+            Lang::En => format!("{}: not pinned", path.display()),
+            Lang::Zh => format!("{}: 没有收藏", path.display()),
+        }
+    }
+
+    pub fn no_pins(&self) -> &'static str {
+        match self {
+            Lang::En => "nothing is pinned yet; pin a directory with `tree-cli pin <DIR>`",
+            Lang::Zh => "还没有收藏的目录，用 `tree-cli pin <DIR>` 收藏",
+        }
+    }
+
+    pub fn pins_file_unknown(&self) -> &'static str {
+        match self {
+            Lang::En => "cannot tell where to keep pins; set TREE_CLI_PINS to a file path",
+            Lang::Zh => "无法确定收藏文件的位置，请用 TREE_CLI_PINS 指定文件路径",
+        }
+    }
+
+    pub fn pin_options_invalid(&self, path: &Path, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("{}: invalid pin options: {}", path.display(), err),
+            Lang::Zh => format!("{}: 收藏的选项无效: {}", path.display(), err),
+        }
+    }
+
+    pub fn pin_subcommand(&self) -> &'static str {
+        match self {
+            Lang::En => "a pin cannot run a subcommand",
+            Lang::Zh => "收藏的选项中不能有子命令",
+        }
+    }
+
+    pub fn verify_failed(&self, num_entries: usize) -> String {
+        match self {
+            Lang::En => format!(
+                "verification failed: {} entries differ from the manifest",
+                num_entries
+            ),
+            Lang::Zh => format!("校验失败: {} 个条目与清单不一致", num_entries),
+        }
+    }
+
+    pub fn export_failed(&self, path: &Path, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("could not write {}: {}", path.display(), err),
+            Lang::Zh => format!("无法写入 {}: {}", path.display(), err),
+        }
+    }
+
+    pub fn invalid_color_rule(&self, pattern: &str, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("color rule for '{}': {}", pattern, err),
+            Lang::Zh => format!("'{}' 的颜色规则无效: {}", pattern, err),
+        }
+    }
+
+    pub fn invalid_dircolors(&self, path: &Path, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("{}: invalid dircolors database: {}", path.display(), err),
+            Lang::Zh => format!("{}: dircolors 配色文件无效: {}", path.display(), err),
+        }
+    }
+
+    pub fn watch_failed(&self, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("could not watch for changes: {}", err),
+            Lang::Zh => format!("无法监视变化: {}", err),
+        }
+    }
+
+    pub fn memory_limit_reached(&self, megabytes: usize) -> String {
+        match self {
+            Lang::En => format!(
+                "the tree does not fit in {} MB; listing the rest as it is read, without directory totals or sorting other than by name",
+                megabytes
+            ),
+            Lang::Zh => format!(
+                "目录树超出了 {} MB 的内存上限，其余条目边读取边显示，不再统计目录大小，也只按名称排序",
+                megabytes
+            ),
+        }
+    }
+
+    pub fn no_files_listed(&self) -> &'static str {
+        match self {
+            Lang::En => "no files listed",
+            Lang::Zh => "没有列出任何文件",
+        }
+    }
+
+    pub fn git_failed(&self, err: impl Display) -> String {
+        match self {
+            Lang::En => format!("could not list tracked files: {}", err),
+            Lang::Zh => format!("无法获取 git 跟踪的文件: {}", err),
+        }
+    }
+
+    pub fn entries_not_shown(&self, num_entries: usize) -> String {
+        match self {
+            Lang::En => format!("… {} entries not shown", num_entries),
+            Lang::Zh => format!("… 还有 {} 个条目未显示", num_entries),
+        }
+    }
+
+    pub fn flags_unsupported(&self) -> &'static str {
+        match self {
+            Lang::En => "--flags needs BSD file flags, which only macOS and FreeBSD have",
+            Lang::Zh => "--flags 需要 BSD 文件标志，只有 macOS 和 FreeBSD 支持",
+        }
+    }
+
+    pub fn drives_unsupported(&self) -> &'static str {
+        match self {
+            Lang::En => "--drives is only available on Windows",
+            Lang::Zh => "--drives 只能在 Windows 上使用",
+        }
+    }
+
+    pub fn other_filesystem(&self) -> &'static str {
+        match self {
+            Lang::En => "[other filesystem]",
+            Lang::Zh => "[其他文件系统]",
+        }
+    }
+
+    pub fn special_kind(&self, kind: SpecialKind) -> &'static str {
+        match (self, kind) {
+            (Lang::En, SpecialKind::Fifo) => "[fifo]",
+            (Lang::En, SpecialKind::Socket) => "[socket]",
+            (Lang::En, SpecialKind::CharDevice) => "[char device]",
+            (Lang::En, SpecialKind::BlockDevice) => "[block device]",
+            (Lang::Zh, SpecialKind::Fifo) => "[管道]",
+            (Lang::Zh, SpecialKind::Socket) => "[套接字]",
+            (Lang::Zh, SpecialKind::CharDevice) => "[字符设备]",
+            (Lang::Zh, SpecialKind::BlockDevice) => "[块设备]",
+        }
+    }
+
+    pub fn target_unreadable(&self) -> &'static str {
+        match self {
+            Lang::En => "[link target unreadable]",
+            Lang::Zh => "[无法读取链接目标]",
+        }
+    }
+
+    pub fn single_root_only(&self) -> &'static str {
+        match self {
+            Lang::En => "--output-format, -H and --verify take a single directory",
+            Lang::Zh => "--output-format、-H 和 --verify 只能用于一个目录",
+        }
+    }
+
+    pub fn entries_scanned(&self, num_entries: usize) -> String {
+        match self {
+            Lang::En => format!("{} entries scanned", num_entries),
+            Lang::Zh => format!("已扫描 {} 个条目", num_entries),
+        }
+    }
+
+    /// `bench` 子命令的结果，每个阶段一行
+    pub fn bench_report(&self, report: &BenchReport) -> String {
+        let phases = match self {
+            Lang::En => ["read dir", "stat", "filter", "build", "render", "total"],
+            Lang::Zh => ["读取目录", "元数据", "过滤", "建树", "渲染", "合计"],
+        };
+        let times = [
+            report.scan.read_dir,
+            report.scan.stat,
+            report.scan.filter,
+            report.build,
+            report.render,
+            report.total,
+        ];
+        let total = report.total.as_secs_f64();
+        let mut lines = String::new();
+        for (phase, time) in phases.iter().zip(times) {
+            let seconds = time.as_secs_f64();
+            let share = if total > 0.0 {
+                seconds / total * 100.0
+            } else {
+                0.0
+            };
+            // 中文字符占两列，按显示宽度补齐
+            let padding = " ".repeat(10usize.saturating_sub(phase.width()));
+            lines.push_str(&format!(
+                "{}{}{:>10.3}s {:>6.1}%\n",
+                phase, padding, seconds, share
+            ));
+        }
+        lines.push_str(&match self {
+            Lang::En => format!(
+                "{} entries, {:.0} entries/s",
+                report.entries,
+                report.entries_per_second()
+            ),
+            Lang::Zh => format!(
+                "{} 个条目，每秒 {:.0} 个",
+                report.entries,
+                report.entries_per_second()
+            ),
+        });
+        lines
+    }
+
+    /// `--timing` 的统计；没有系统 I/O 计数的平台上省略后半部分
+    pub fn timing_report(&self, report: &TimingReport) -> String {
+        let seconds = report.elapsed.as_secs_f64();
+        let rate = report.entries_per_second();
+        let scan = &report.scan;
+        let mut line = match self {
+            Lang::En => format!(
+                "{:.3}s elapsed, {:.0} entries/s, {} directories read, {} entries stat'ed on {} \
+                 threads",
+                seconds,
+                rate,
+                scan.dirs_read,
+                scan.stats,
+                scan.stat_threads.max(1)
+            ),
+            Lang::Zh => format!(
+                "耗时 {:.3} 秒，每秒 {:.0} 个条目，读取 {} 个目录，用 {} 个线程读取 {} 个条目的元数据",
+                seconds,
+                rate,
+                scan.dirs_read,
+                scan.stat_threads.max(1),
+                scan.stats
+            ),
+        };
+        if let Some(io) = report.io {
+            line.push_str(&match self {
+                Lang::En => format!(
+                    ", {} read syscalls, {} write syscalls, {} read from disk",
+                    io.read_syscalls,
+                    io.write_syscalls,
+                    human_size(io.read_bytes)
+                ),
+                Lang::Zh => format!(
+                    "，{} 次读系统调用，{} 次写系统调用，从磁盘读取 {}",
+                    io.read_syscalls,
+                    io.write_syscalls,
+                    human_size(io.read_bytes)
+                ),
+            });
+        }
+        line
+    }
+
+    /// `--explain` 的结果：是否列出以及起决定作用的规则，`decided_by` 是被排除的上级目录
+    pub fn explain(&self, path: &Path, decided_by: &Path, verdict: &Verdict) -> String {
+        let parent = (decided_by != path).then(|| decided_by.display());
+        let path = path.display();
+        match self {
+            Lang::En => {
+                let outcome = if verdict.listed {
+                    "listed"
+                } else {
+                    "not listed"
+                };
+                let reason = match &verdict.rule {
+                    Rule::Hidden => "hidden (use -a to list it)".to_string(),
+                    Rule::Untracked => "not tracked by git (--tracked)".to_string(),
+                    Rule::ExcludePath(prefix) => {
+                        format!("under --exclude-path {}", prefix.display())
+                    }
+                    Rule::ExcludePattern(pattern) if verdict.container => format!(
+                        "matches -E '{}', shown only to hold entries brought back by '!' rules",
+                        pattern
+                    ),
+                    Rule::ExcludePattern(pattern) => format!("matches -E '{}'", pattern),
+                    Rule::InsideExcluded => "inside a directory excluded by -E".to_string(),
+                    Rule::Reincluded(pattern) => format!("brought back by -E '{}'", pattern),
+                    Rule::NotHidden if verdict.container => {
+                        "not hidden, shown only to hold hidden entries (--hidden-only)".to_string()
+                    }
+                    Rule::NotHidden => "not hidden (--hidden-only)".to_string(),
+                    Rule::IncludePattern(pattern) if verdict.listed => {
+                        format!("matches -P '{}'", pattern)
+                    }
+                    Rule::IncludePattern(pattern) => {
+                        format!("does not match -P '{}'", pattern)
+                    }
+                    Rule::Directory => {
+                        "directories are not matched against -P; shown if they contain a match"
+                            .to_string()
+                    }
+                    Rule::Default => "no rule excludes it".to_string(),
+                };
+                match parent {
+                    Some(parent) => format!("{}: {}, parent {}: {}", path, outcome, parent, reason),
+                    None => format!("{}: {}, {}", path, outcome, reason),
+                }
+            }
+            Lang::Zh => {
+                let outcome = if verdict.listed {
+                    "列出"
+                } else {
+                    "不列出"
+                };
+                let reason = match &verdict.rule {
+                    Rule::Hidden => "隐藏文件（使用 -a 列出）".to_string(),
+                    Rule::Untracked => "未被 git 跟踪（--tracked）".to_string(),
+                    Rule::ExcludePath(prefix) => {
+                        format!("位于 --exclude-path {} 之下", prefix.display())
+                    }
+                    Rule::ExcludePattern(pattern) if verdict.container => format!(
+                        "匹配 -E '{}'，只为容纳被 '!' 规则重新包含的条目而显示",
+                        pattern
+                    ),
+                    Rule::ExcludePattern(pattern) => format!("匹配 -E '{}'", pattern),
+                    Rule::InsideExcluded => "所在目录被 -E 排除".to_string(),
+                    Rule::Reincluded(pattern) => format!("被 -E '{}' 重新包含", pattern),
+                    Rule::NotHidden if verdict.container => {
+                        "不是隐藏条目，只为容纳其中的隐藏条目而显示（--hidden-only）".to_string()
+                    }
+                    Rule::NotHidden => "不是隐藏条目（--hidden-only）".to_string(),
+                    Rule::IncludePattern(pattern) if verdict.listed => {
+                        format!("匹配 -P '{}'", pattern)
+                    }
+                    Rule::IncludePattern(pattern) => {
+                        format!("不匹配 -P '{}'", pattern)
+                    }
+                    Rule::Directory => "目录不与 -P 比较，包含匹配的文件时才显示".to_string(),
+                    Rule::Default => "没有规则排除它".to_string(),
+                };
+                match parent {
+                    Some(parent) => {
+                        format!("{}：{}，上级目录 {}：{}", path, outcome, parent, reason)
+                    }
+                    None => format!("{}：{}，{}", path, outcome, reason),
+                }
+            }
+        }
+    }
+}
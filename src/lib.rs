@@ -0,0 +1,193 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::datetime::TimeKind;
+use crate::export::OutputFormat;
+use crate::filter::ExcludeRules;
+use crate::git::TrackedFiles;
+use crate::html::HtmlOptions;
+use crate::i18n::Lang;
+use crate::pattern::Pattern;
+use crate::size::{ByteFormat, SizeKind};
+use crate::sort::SortKey;
+use crate::symbol::Charset;
+use crate::theme::{ColorRules, DirColors};
+
+pub mod archive;
+pub mod assertion;
+pub mod badges;
+pub mod bench;
+pub mod clipboard;
+pub mod compare;
+pub mod core;
+pub mod datetime;
+pub mod drives;
+pub mod errors;
+pub mod export;
+pub mod file_iterator;
+pub mod filter;
+pub mod flags;
+pub mod generate;
+pub mod git;
+pub mod hidden;
+pub mod html;
+pub mod i18n;
+pub mod mounts;
+pub mod mtree;
+pub mod owner;
+pub mod parquet;
+pub mod pattern;
+pub mod pins;
+pub mod progress;
+pub mod project;
+pub mod render;
+pub mod replay;
+pub mod scaffold;
+pub mod size;
+pub mod sort;
+pub mod sqlite;
+pub mod symbol;
+pub mod terminal;
+#[cfg(feature = "test_utils")]
+pub mod test_utils;
+pub mod theme;
+pub mod verify;
+pub mod watch;
+
+#[derive(Default, Clone)]
+pub struct Config {
+    pub traversal: TraversalOptions,
+    pub filter: FilterOptions,
+    pub display: DisplayOptions,
+}
+
+/// 遍历目录时使用的选项
+#[derive(Clone)]
+pub struct TraversalOptions {
+    pub max_level: usize,
+    pub follow_links: bool,
+    pub locale_sort: bool,
+    /// 依次比较的排序键，前面的键相同时才比较后面的键
+    pub sort_keys: Vec<SortKey>,
+    /// 目录排在文件之前
+    pub dirs_first: bool,
+    /// `-U`：不排序，按 readdir 返回的顺序列出
+    pub unsorted: bool,
+    /// 继续统计 `-L` 之下没有显示的条目
+    pub count_below: bool,
+    /// 在 `-L` 截断的目录下显示一行占位说明
+    pub show_truncated: bool,
+    /// 用多个线程读取大目录中条目的元数据
+    pub parallel_stat: bool,
+    /// `parallel_stat` 时使用的线程数
+    pub threads: usize,
+    /// 单次读取目录或元数据的超时时间，`None` 表示一直等待
+    pub timeout: Option<Duration>,
+}
+
+impl Default for TraversalOptions {
+    fn default() -> Self {
+        TraversalOptions {
+            max_level: usize::MAX,
+            follow_links: false,
+            locale_sort: false,
+            sort_keys: vec![SortKey::Name],
+            dirs_first: false,
+            unsorted: false,
+            count_below: false,
+            show_truncated: false,
+            parallel_stat: false,
+            threads: file_iterator::default_threads(),
+            timeout: None,
+        }
+    }
+}
+
+/// 决定哪些条目会被列出的选项
+#[derive(Default, Clone)]
+pub struct FilterOptions {
+    pub show_all: bool,
+    /// 只列出隐藏条目，其他目录只作为通往它们的路径显示
+    pub hidden_only: bool,
+    /// `-P`，只对文件生效；编译好的匹配器在各个迭代器之间共享
+    pub include_glob: Option<Arc<Pattern>>,
+    /// `-E`，对文件和目录都生效，排除的目录连同其内容一起去掉；优先于 `-P`
+    pub exclude_glob: Arc<ExcludeRules>,
+    /// `--exclude-path`，已转换为绝对路径，按路径前缀剪掉整棵子树
+    pub exclude_paths: Vec<PathBuf>,
+    /// 只列出空目录
+    pub empty_dirs_only: bool,
+    /// 不显示比这更浅的层级，0 和 1 都表示不限制
+    pub min_depth: usize,
+    /// 只列出 git 跟踪的文件
+    pub tracked: Option<TrackedFiles>,
+}
+
+/// 输出相关的选项
+#[derive(Default, Clone)]
+pub struct DisplayOptions {
+    pub colorful: bool,
+    /// 按修改时间的远近给文件名着色
+    pub age_colors: bool,
+    /// 按大小给文件名着色
+    pub size_colors: bool,
+    /// 项目配置中的颜色规则，优先于内置的颜色
+    pub color_rules: ColorRules,
+    /// `--dircolors` 读入的配色，给出时代替内置的颜色
+    pub dircolors: Option<DirColors>,
+    pub charset: Charset,
+    /// 每一层缩进的宽度，见 [`symbol::set_line_prefix`]
+    pub indent: usize,
+    /// 超过这个深度的外层前缀合并成 `…`
+    pub max_prefix_depth: Option<usize>,
+    /// 显示修改时间时使用的 strftime 格式，`None` 表示不显示
+    pub time_format: Option<String>,
+    /// 在元数据列中显示文件大小
+    pub show_size: bool,
+    /// 在元数据列中显示所有者和所属组
+    pub show_owner: bool,
+    pub show_group: bool,
+    /// 显示每个目录下占用的 inode 数
+    pub inode_usage: bool,
+    /// `-s` 和 `--du` 使用文件长度还是占用的磁盘空间
+    pub size_kind: SizeKind,
+    /// `-s` 中每个条目的字节数的写法
+    pub byte_format: ByteFormat,
+    /// 所有者和组显示为数字，不查找名字
+    pub numeric_ids: bool,
+    /// 显示 BSD 文件标志（`uchg`、`hidden` 等）
+    pub show_flags: bool,
+    /// 在元数据列中显示占父目录大小比例的条形图
+    pub bars: bool,
+    /// 跟随链接时元数据列显示目标的信息，而不是链接自身的信息
+    pub target_meta: bool,
+    /// 显示哪一个时间戳
+    pub time_kind: TimeKind,
+    /// 元数据列放在树形前缀之前并对齐
+    pub meta_first: bool,
+    /// `--columns` 时可用的终端宽度
+    pub columns: Option<usize>,
+    /// 超过这个长度的名字截断显示，见 [`symbol::shorten_name`]
+    pub max_name_len: Option<usize>,
+    pub mark_sparse: bool,
+    /// 在磁盘上没有任何条目的目录后面标出 `(empty)`
+    pub mark_empty: bool,
+    /// 在名称前显示 emoji 图标
+    pub emoji: bool,
+    /// 标出挂载点，即与上级目录不在同一文件系统上的目录
+    pub show_fs: bool,
+    /// 在 git 仓库的根目录后面标出分支和是否有改动
+    pub git_repos: bool,
+    /// 在有 Cargo.toml、package.json 等文件的目录后面标出项目类型
+    pub project_badges: bool,
+    /// 把同一目录下扩展名相同的文件合并显示
+    pub group_by_ext: bool,
+    pub disk_usage: bool,
+    pub copy: bool,
+    /// 以 HTML 页面输出
+    pub html: Option<HtmlOptions>,
+    /// 以 CSV / NDJSON / JSON 等机器可读格式输出
+    pub output_format: Option<&'static OutputFormat>,
+    pub lang: Lang,
+}
@@ -1,71 +1,1445 @@
-use std::path::Path;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{env, fs};
 
-use clap::Parser;
-use globset::{Glob, GlobMatcher};
+use std::io::{self, IsTerminal, Write};
 
-use crate::core::{DirSummary, DirTree};
+use clap::builder::RangedU64ValueParser;
+use clap::{Parser, Subcommand};
 
-mod core;
-mod file_iterator;
-mod filter;
-mod symbol;
+use tree_cli::archive::compare_archive;
+use tree_cli::assertion::{Thresholds, Violation};
+use tree_cli::bench::{bench, IoCounters, TimingReport};
+use tree_cli::clipboard::copy_to_clipboard;
+use tree_cli::compare::{compare, render_changes, Scan};
+use tree_cli::core::{
+    build_tree, build_tree_within, DirSummary, ReportStyle, StreamingScan, Traversal, Tree,
+};
+use tree_cli::datetime::{is_valid_format, TimeKind, DEFAULT_TIME_FORMAT};
+use tree_cli::drives::logical_drives;
+use tree_cli::errors::{item_errors, traversal_errors, ErrorFormat, TraversalError};
+use tree_cli::export::{self, OutputFormat};
+use tree_cli::file_iterator::{default_threads, FileIterator, ScanTimings};
+use tree_cli::filter::ExcludeRules;
+use tree_cli::flags;
+use tree_cli::generate::{generate, GenerateOptions};
+use tree_cli::git::{tracked_files, TrackedFiles};
+use tree_cli::html::{render_html, HtmlOptions, DEFAULT_TITLE};
+use tree_cli::i18n::Lang;
+use tree_cli::mounts::{MountTable, ScanProfile};
+use tree_cli::parquet::write_parquet;
+use tree_cli::pattern::{Pattern, MACOS_NOISE};
+use tree_cli::pins::{pins_file, Pins};
+use tree_cli::progress::Progress;
+use tree_cli::project::{find_config, ProjectConfig};
+use tree_cli::render::{render, render_stream, Renderer};
+use tree_cli::replay::render_scan;
+use tree_cli::scaffold;
+use tree_cli::size::{human_size, ByteFormat, SizeKind};
+use tree_cli::sort::SortKey;
+use tree_cli::sqlite::write_sqlite;
+use tree_cli::symbol::{encode_cp437, Charset, DEFAULT_INDENT, MIN_NAME_LEN};
+use tree_cli::terminal::Terminal;
+use tree_cli::theme::{ColorRules, DirColors};
+use tree_cli::verify::{verify, Manifest};
+use tree_cli::watch::{EventFormat, Watcher};
+use tree_cli::{Config, DisplayOptions, FilterOptions, TraversalOptions};
 
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None, author)]
+#[derive(Parser, Debug, Clone)]
+#[command(version, about, long_about = None, author, args_override_self = true)]
 struct Args {
     /// Show all files (include hidden files)
     #[arg(short = 'a', long = "all")]
     show_all: bool,
+    /// List only hidden entries, with the directories leading to them
+    #[arg(long = "hidden-only")]
+    hidden_only: bool,
     /// Turn colorization on always
     #[arg(short = 'C', long = "color")]
     color_on: bool,
     /// Turn colorization off always
     #[arg(short = 'N', long = "no-color")]
     color_off: bool,
-    /// Directory you want to search
+    /// Color names by age: recently changed entries bright, old ones dim
+    #[arg(long = "age-colors")]
+    age_colors: bool,
+    /// Color file names by size so large files stand out
+    #[arg(long = "size-colors", conflicts_with = "age_colors")]
+    size_colors: bool,
+    /// Color names with a dircolors database (the DIR_COLORS format), like `ls` does
+    #[arg(
+        long = "dircolors",
+        value_name = "FILE",
+        conflicts_with_all = ["age_colors", "size_colors"]
+    )]
+    dircolors: Option<PathBuf>,
+    /// Character set used for the indentation lines
+    #[arg(long = "charset", value_enum, default_value = "utf8")]
+    charset: Charset,
+    /// Width of each indentation level, from 2 to 16 columns
+    #[arg(
+        long = "indent",
+        value_name = "N",
+        default_value_t = DEFAULT_INDENT,
+        value_parser = RangedU64ValueParser::<usize>::new().range(2..=16)
+    )]
+    indent: usize,
+    /// Indent by two columns per level (same as --indent 2), for deep trees and narrow pages
+    #[arg(long = "compact", conflicts_with = "indent")]
+    compact: bool,
+    /// Draw at most <N> levels of branch lines in front of an entry, collapsing the outer ones
+    /// into a single `…` followed by their number
+    #[arg(
+        long = "max-prefix-depth",
+        value_name = "N",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    max_prefix_depth: Option<usize>,
+    /// Use IBM code page 437 line graphics (same as --charset cp437)
+    #[arg(short = 'S')]
+    cp437: bool,
+    /// List the available drives (Windows only)
+    #[arg(long = "drives", conflicts_with_all = ["dirs", "from_file"])]
+    drives: bool,
+    /// Directories you want to search
     #[arg(value_name = "DIR", default_value = ".")]
-    dir: String,
-    /// List only those files matching <include_pattern>
+    dirs: Vec<String>,
+    /// Read the directories to search from <file>, one per line (`-` for stdin)
+    #[arg(long = "fromfile", value_name = "file")]
+    from_file: Option<PathBuf>,
+    /// Render a scan saved with --output-format json or ndjson, or with --sqlite, instead of
+    /// listing directories
+    #[arg(
+        long = "from-json",
+        value_name = "FILE",
+        conflicts_with_all = ["dirs", "from_file", "watch", "output_format", "html_base"]
+    )]
+    from_json: Option<PathBuf>,
+    /// Keep running and list the directories again whenever something under them changes
+    #[arg(long = "watch", conflicts_with = "from_file")]
+    watch: bool,
+    /// With --watch, print one event per created, modified or deleted entry instead of the tree
+    #[arg(long = "events", value_enum, value_name = "format", requires = "watch")]
+    events: Option<EventFormat>,
+    /// List only files matching <include_pattern>; a leading `!` lists files that don't match
     #[arg(short = 'P', long = "pattern")]
     include_pattern: Option<String>,
+    /// Do not list files or directories matching <exclude_pattern> (repeatable, wins over -P)
+    #[arg(short = 'E', long = "exclude")]
+    exclude_pattern: Vec<String>,
+    /// Leave out .DS_Store, .Spotlight-V100, .Trashes, __MACOSX and `._*` resource forks
+    #[arg(long = "no-macos-noise")]
+    no_macos_noise: bool,
+    /// Do not list <path> or anything below it; relative paths are taken from the current
+    /// directory (repeatable)
+    #[arg(long = "exclude-path", value_name = "path")]
+    exclude_paths: Vec<PathBuf>,
+    /// List only empty directories (and the directories leading to them)
+    #[arg(long = "empty-dirs")]
+    empty_dirs_only: bool,
+    /// List only files tracked by git (as reported by `git ls-files`)
+    #[arg(long = "tracked")]
+    tracked: bool,
     /// Descend only <level> directories deep
     #[arg(short = 'L', long = "level", default_value_t = usize::max_value())]
     max_level: usize,
+    /// Start the listing at level <N>, showing those entries by their path from the root
+    #[arg(long = "min-depth", value_name = "N", default_value_t = 1)]
+    min_depth: usize,
+    /// Keep counting entries below the -L cutoff and show them on truncated directories
+    #[arg(long = "count-below")]
+    count_below: bool,
+    /// Print a placeholder line under directories truncated by -L
+    #[arg(long = "show-truncated")]
+    show_truncated: bool,
+    /// Give up on a directory read or stat after <secs> seconds and mark the entry instead
+    #[arg(long = "timeout", value_name = "secs", value_parser = parse_seconds)]
+    timeout: Option<Duration>,
+    /// How to scan: `network` stats entries in parallel, shows progress right away and gives up
+    /// on operations slower than 10 seconds (unless --timeout is given); `auto` picks it for
+    /// roots on NFS, SMB or FUSE mounts (detected on Linux)
+    #[arg(long = "profile", value_enum, default_value = "auto")]
+    profile: ScanProfile,
+    /// Stat entries on <N> threads (default: one per CPU, at most 8), with or without
+    /// --timeout; 1 reads everything in order, which suits spinning disks
+    #[arg(
+        long = "threads",
+        value_name = "N",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    threads: Option<usize>,
+    /// Keep at most about <MB> megabytes of the tree in memory; larger trees are listed as
+    /// they are read, without directory totals or sorting other than by name
+    #[arg(
+        long = "max-memory",
+        value_name = "MB",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..),
+        conflicts_with_all = [
+            "output_format", "html_base", "sqlite", "parquet", "verify_manifest", "summary_json",
+            "group_by_ext", "inode_usage", "empty_dirs_only", "min_depth", "from_json"
+        ]
+    )]
+    max_memory: Option<usize>,
+    /// Follow symbolic links like directories
+    #[arg(short = 'l', long = "follow")]
+    follow_links: bool,
+    /// With -l, show the size and dates of link targets instead of the links themselves
+    #[arg(long = "target-meta", requires = "follow_links")]
+    target_meta: bool,
+    /// Tell whether <path> would be listed with the current filters and which rule decided
+    #[arg(long = "explain", value_name = "path")]
+    explain: Option<PathBuf>,
+    /// Sort names using the collation rules of the current locale
+    #[arg(long = "locale-sort", conflicts_with = "bytewise")]
+    locale_sort: bool,
+    /// Order entries by name, entries, size or mtime; give several keys separated by commas
+    /// (e.g. `size,name`) to break ties, which otherwise fall back to the name
+    #[arg(
+        long = "sort",
+        value_enum,
+        value_delimiter = ',',
+        value_name = "keys",
+        default_value = "name"
+    )]
+    sort_keys: Vec<SortKey>,
+    /// List directories before files
+    #[arg(long = "dirsfirst")]
+    dirs_first: bool,
+    /// Do not sort: list entries in the order the filesystem returns them, which is fastest
+    /// for piping huge trees into other tools
+    #[arg(
+        short = 'U',
+        long = "no-sort",
+        conflicts_with_all = ["sort_keys", "dirs_first", "locale_sort", "bytewise"]
+    )]
+    no_sort: bool,
+    /// Ignore `.tree-cli.toml` in the listed directory and its ancestors
+    #[arg(long = "no-config")]
+    no_config: bool,
+    /// Sort names by their raw bytes (default)
+    #[arg(long = "bytewise")]
+    bytewise: bool,
+    /// Print the owner of each entry
+    #[arg(short = 'u')]
+    show_owner: bool,
+    /// Print the group of each entry
+    #[arg(short = 'g')]
+    show_group: bool,
+    /// With -u or -g, print user and group ids instead of looking up their names
+    #[arg(long = "numeric-ids")]
+    numeric_ids: bool,
+    /// Print BSD file flags such as uchg, hidden and nodump (macOS and FreeBSD)
+    #[arg(long = "flags")]
+    show_flags: bool,
+    /// Print the size of each entry and the total size in the report
+    #[arg(short = 's', long = "size")]
+    show_size: bool,
+    /// Print exact byte counts, also for the total in the report, implying -s; `grouped` adds
+    /// the locale's thousands separator
+    #[arg(
+        long = "bytes",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "plain",
+        value_name = "format"
+    )]
+    bytes: Option<ByteFormat>,
+    /// Print a bar showing each entry's share of its parent directory's size
+    #[arg(long = "bars")]
+    bars: bool,
+    /// Print the date of last modification
+    #[arg(short = 'D', long = "date")]
+    show_date: bool,
+    /// Format dates with a strftime-style <fmt> (implies -D)
+    #[arg(long = "timefmt", value_name = "fmt")]
+    time_format: Option<String>,
+    /// Which timestamp -D prints and --sort mtime orders by
+    #[arg(long = "time", value_enum, default_value = "mtime")]
+    time_kind: TimeKind,
+    /// Print metadata before the indentation lines, aligned in a column
+    #[arg(long = "metafirst")]
+    meta_first: bool,
+    /// Lay out the files of a directory in several columns when the terminal is wide enough
+    #[arg(long = "columns", conflicts_with_all = ["output_format", "html_base"])]
+    columns: bool,
+    /// Shorten names longer than <N> characters to their start, `…`, a short hash of the full
+    /// name and the extension, so that shortened names stay distinct
+    #[arg(
+        long = "max-name-len",
+        value_name = "N",
+        value_parser = RangedU64ValueParser::<usize>::new().range(MIN_NAME_LEN as u64..),
+        conflicts_with_all = ["output_format", "html_base"]
+    )]
+    max_name_len: Option<usize>,
+    /// Mark zero-byte files and sparse files
+    #[arg(long = "mark-sparse")]
+    mark_sparse: bool,
+    /// Mark directories that are empty on disk with `(empty)`, telling them apart from
+    /// directories whose entries are filtered out or below the depth limit
+    #[arg(long = "mark-empty")]
+    mark_empty: bool,
+    /// Prefix entries with emoji icons (📁 📄 🔗 ⚙️), which need no patched font
+    #[arg(long = "emoji")]
+    emoji: bool,
+    /// Mark mount points, the directories on a different filesystem than their parent, with
+    /// the filesystem's type
+    #[arg(long = "show-fs")]
+    show_fs: bool,
+    /// Mark directories that are the root of a git repository with their branch and whether
+    /// they have uncommitted changes, e.g. `my-repo (main ✗)`
+    #[arg(long = "git-repos")]
+    git_repos: bool,
+    /// Mark directories holding a Cargo.toml, package.json, pyproject.toml or go.mod with
+    /// a [rust], [node], [python] or [go] badge
+    #[arg(long = "project-badges")]
+    project_badges: bool,
+    /// Collapse files sharing an extension into one `*.ext (N)` entry per directory
+    #[arg(
+        long = "group-by-ext",
+        conflicts_with_all = ["output_format", "html_base", "verify_manifest"]
+    )]
+    group_by_ext: bool,
+    /// Report the total size of listed files, counting hard links once
+    #[arg(long = "du")]
+    disk_usage: bool,
+    /// Use the space allocated on disk for -s and --du instead of the file length, or `both`
+    #[arg(
+        long = "disk-usage",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value = "apparent",
+        default_missing_value = "allocated",
+        value_name = "kind"
+    )]
+    size_kind: SizeKind,
+    /// Print how many inodes each directory uses, counting everything below it
+    #[arg(long = "inode-usage")]
+    inode_usage: bool,
+    /// Add a line to the report counting entries by type: regular files, executables,
+    /// directories, symlinks, fifos, sockets and devices
+    #[arg(long = "report-types")]
+    report_types: bool,
+    /// Print the elapsed time, entries per second, directories read and, where the OS reports
+    /// them, read/write syscalls and bytes read from disk on stderr after the output; not with
+    /// --quiet
+    #[arg(long = "timing")]
+    timing: bool,
+    /// Print only the report, as one JSON object with counts, sizes, errors and elapsed time
+    #[arg(long = "summary-json", conflicts_with_all = ["output_format", "html_base"])]
+    summary_json: bool,
+    /// How much detail to print in the final report
+    #[arg(long = "report", value_enum, default_value = "short")]
+    report_style: ReportStyle,
+    /// Print machine-readable output instead of the tree: csv, ndjson, json or mtree,
+    /// optionally pinned to a schema version as <name>@<version>; an unknown name lists the
+    /// formats with their schemas
+    #[arg(long = "output-format", value_name = "name[@version]", value_parser = OutputFormat::parse)]
+    output_format: Option<&'static OutputFormat>,
+    /// Also write every listed entry into a new SQLite database at <file>, in an `entries`
+    /// table with path, parent, type, size, mtime and depth
+    #[arg(long = "sqlite", value_name = "file")]
+    sqlite: Option<PathBuf>,
+    /// Also write every listed entry to <file> in Apache Parquet format, with the same columns
+    /// as --sqlite
+    #[arg(long = "parquet", value_name = "file")]
+    parquet: Option<PathBuf>,
+    /// Compare the tree against an NDJSON manifest exported earlier and mark each entry
+    #[arg(
+        long = "verify",
+        value_name = "manifest",
+        conflicts_with_all = ["output_format", "html_base"]
+    )]
+    verify_manifest: Option<PathBuf>,
+    /// Order of the entries in csv, ndjson and mtree output and in --sqlite and --parquet
+    /// files: `dfs` puts each directory's entries right after it, like the tree, `bfs` lists a
+    /// whole level before the next
+    #[arg(long = "traversal", value_enum, default_value = "dfs")]
+    traversal: Traversal,
+    /// Print an HTML page with links prefixed by <baseHREF>
+    #[arg(short = 'H', value_name = "baseHREF")]
+    html_base: Option<String>,
+    /// Title of the HTML page
+    #[arg(short = 'T', value_name = "title", default_value = DEFAULT_TITLE)]
+    html_title: String,
+    /// Turn off hyperlinks in HTML output
+    #[arg(long = "nolinks")]
+    no_links: bool,
+    /// Use the contents of <file> instead of the default HTML intro
+    #[arg(long = "hintro", value_name = "file")]
+    html_intro: Option<PathBuf>,
+    /// Use the contents of <file> instead of the default HTML outro
+    #[arg(long = "houtro", value_name = "file")]
+    html_outro: Option<PathBuf>,
+    /// With -H, make directories collapsible and add a filter box to the page
+    #[arg(long = "html-interactive", requires = "html_base")]
+    html_interactive: bool,
+    /// Exit with an error when more than <N> files are listed
+    #[arg(long = "assert-max-files", value_name = "N")]
+    assert_max_files: Option<usize>,
+    /// Exit with an error when more than <N> directories are listed
+    #[arg(long = "assert-max-dirs", value_name = "N")]
+    assert_max_dirs: Option<usize>,
+    /// Exit with an error when entries are nested deeper than <N> levels
+    #[arg(long = "assert-max-depth", value_name = "N")]
+    assert_max_depth: Option<usize>,
+    /// Exit with an error when listed files add up to more than <BYTES>
+    #[arg(long = "assert-max-size", value_name = "BYTES")]
+    assert_max_size: Option<u64>,
+    /// Exit with status 4 when no files are listed, e.g. because nothing matches -P
+    #[arg(long = "fail-if-empty")]
+    fail_if_empty: bool,
+    /// With `json`, also print each traversal error on stderr as a JSON object
+    #[arg(long = "errors", value_enum, default_value = "text")]
+    error_format: ErrorFormat,
+    /// Never show the progress line on stderr during long scans
+    #[arg(long = "no-progress")]
+    no_progress: bool,
+    /// Print only the tree: no report line and no warnings on stderr
+    #[arg(long = "quiet")]
+    quiet: bool,
+    /// Also copy the rendered tree (without colors) to the system clipboard
+    #[arg(long = "copy")]
+    copy: bool,
+    /// Language of the report line and messages (defaults to LANG)
+    #[arg(long = "lang", value_enum)]
+    lang: Option<Lang>,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-struct Config {
-    colorful: bool,
-    show_all: bool,
-    max_level: usize,
-    include_glob: Option<GlobMatcher>,
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Time each phase of listing <DIR> with the options given before `bench`
+    Bench {
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+    },
+    /// Print what changed between two scans saved with --output-format json or ndjson, or
+    /// with --sqlite, without touching the filesystem
+    Compare {
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+    },
+    /// Print what differs between <DIR> and a tar or tar.gz archive of it, without extracting
+    /// the archive; `+` marks entries only on disk, `-` entries only in the archive
+    Diff {
+        /// Also compare the SHA-256 of every file, not just its size
+        #[arg(long = "hash")]
+        hash: bool,
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+    },
+    /// Create a synthetic tree in <DIR> for demos, bug reports and benchmarks; the same options
+    /// always create the same tree
+    Generate {
+        /// Deepest level files are placed at, below <DIR>
+        #[arg(long = "depth", default_value_t = 3)]
+        depth: usize,
+        /// Number of files to create
+        #[arg(long = "files", default_value_t = 100)]
+        files: usize,
+        /// Seed for the names, nesting and file sizes
+        #[arg(long = "seed", default_value_t = 0)]
+        seed: u64,
+        /// Directory to create the tree in; must not exist yet or be empty
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+    },
+    /// Create the directories and empty files described in <FILE>: the output of tree-cli or
+    /// GNU tree, or a list indented with spaces where names ending in `/` are directories
+    Mkdir {
+        /// File with the tree description (`-` for stdin)
+        #[arg(long = "from", value_name = "FILE")]
+        from: PathBuf,
+        /// Directory to create the entries in
+        #[arg(value_name = "DIR", default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Pin <DIR> so that `tree-cli pins` lists it, with the options given after `--`, e.g.
+    /// `tree-cli pin ~/src/app -- -L 2 --dirsfirst`; pinning it again replaces the options
+    Pin {
+        /// Remove the pin of <DIR> instead
+        #[arg(long = "remove", conflicts_with = "options")]
+        remove: bool,
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(last = true, value_name = "OPTIONS")]
+        options: Vec<String>,
+    },
+    /// List every pinned directory with its own options; options given before `pins` apply to
+    /// every pin and win over a pin's own
+    Pins {
+        /// Print the pinned directories and their options instead of their trees
+        #[arg(long = "list")]
+        list: bool,
+    },
+}
+
+/// `--profile network` 时单个操作的默认超时
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 正常结束的退出码
+const EXIT_SUCCESS: i32 = 0;
+/// 参数错误的退出码
+const EXIT_USAGE: i32 = 1;
+/// 运行时错误的退出码
+const EXIT_FAILURE: i32 = 2;
+/// `--assert-*`、`--verify` 检查未通过或 `diff` 发现不同时的退出码
+const EXIT_ASSERT: i32 = 3;
+/// `--fail-if-empty` 时没有列出任何文件的退出码
+const EXIT_EMPTY: i32 = 4;
+
+struct CliError {
+    code: i32,
+    message: String,
+}
+
+impl CliError {
+    fn usage(message: impl Display) -> CliError {
+        CliError {
+            code: EXIT_USAGE,
+            message: message.to_string(),
+        }
+    }
+
+    fn assertion(violations: Vec<Violation>) -> CliError {
+        let lines: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+        CliError {
+            code: EXIT_ASSERT,
+            message: lines.join("\n"),
+        }
+    }
+
+    fn verification(message: impl Display) -> CliError {
+        CliError {
+            code: EXIT_ASSERT,
+            message: message.to_string(),
+        }
+    }
+
+    fn empty(message: impl Display) -> CliError {
+        CliError {
+            code: EXIT_EMPTY,
+            message: message.to_string(),
+        }
+    }
+
+    fn failure(message: impl Display) -> CliError {
+        CliError {
+            code: EXIT_FAILURE,
+            message: message.to_string(),
+        }
+    }
+
+    /// 写入输出失败；管道另一端提前退出（例如 `head`）时安静地结束
+    fn output(lang: Lang, err: io::Error) -> CliError {
+        if err.kind() == io::ErrorKind::BrokenPipe {
+            CliError {
+                code: EXIT_SUCCESS,
+                message: String::new(),
+            }
+        } else {
+            CliError::failure(lang.execution_failure(err))
+        }
+    }
 }
 
 fn main() {
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(err) => {
+            // `--help` 和 `--version` 正常结束，其他解析错误与检查参数时发现的错误一样是用法错误
+            let code = if err.use_stderr() {
+                EXIT_USAGE
+            } else {
+                EXIT_SUCCESS
+            };
+            let _ = err.print();
+            process::exit(code);
+        }
+    };
+    let lang = args.lang.unwrap_or_else(Lang::from_env);
+    let result = if args.watch {
+        watch(args, lang)
+    } else {
+        run(args, lang)
+    };
+    if let Err(CliError { code, message }) = result {
+        print_error(&message);
+        process::exit(code);
+    }
+}
+
+fn print_error(message: &str) {
+    for line in message.lines() {
+        eprintln!("tree-cli: {}", line);
+    }
+}
+
+/// `--watch`：先列出一次，之后每批变化重新列出，或者按 `--events` 逐条打印
+fn watch(args: Args, lang: Lang) -> Result<(), CliError> {
+    let roots: Vec<PathBuf> = args.dirs.iter().map(PathBuf::from).collect();
+    let watcher = Watcher::new(&roots).map_err(|err| CliError::failure(lang.watch_failed(err)))?;
+    match args.events {
+        Some(EventFormat::Ndjson) => {
+            while let Some(changes) = watcher.next() {
+                let changes = changes.map_err(|err| CliError::failure(lang.watch_failed(err)))?;
+                let mut out = io::stdout().lock();
+                for change in &changes {
+                    writeln!(out, "{}", change.to_json())
+                        .map_err(|err| CliError::output(lang, err))?;
+                }
+                out.flush().map_err(|err| CliError::output(lang, err))?;
+            }
+        }
+        None => {
+            rerun(&args, lang)?;
+            while let Some(changes) = watcher.wait() {
+                let changes = changes.map_err(|err| CliError::failure(lang.watch_failed(err)))?;
+                if changes.is_empty() {
+                    continue;
+                }
+                if io::stdout().is_terminal() {
+                    // 清屏并把光标移到左上角
+                    print!("\x1b[2J\x1b[H");
+                }
+                rerun(&args, lang)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 重新列出一次；出错只打印出来，继续监视，只有输出管道关闭时才结束
+fn rerun(args: &Args, lang: Lang) -> Result<(), CliError> {
+    match run(args.clone(), lang) {
+        Err(err) if err.message.is_empty() => Err(err),
+        Err(err) => {
+            print_error(&err.message);
+            Ok(())
+        }
+        Ok(()) => Ok(()),
+    }
+}
+
+fn run(args: Args, lang: Lang) -> Result<(), CliError> {
     let Args {
         show_all,
+        hidden_only,
         color_on,
         color_off,
-        dir,
+        age_colors,
+        size_colors,
+        dircolors,
+        charset,
+        indent,
+        compact,
+        max_prefix_depth,
+        cp437,
+        drives,
+        dirs,
+        from_file,
+        from_json,
         include_pattern,
+        exclude_pattern,
+        no_macos_noise,
+        exclude_paths,
+        empty_dirs_only,
+        tracked,
         max_level,
-    } = Args::parse();
-    let path = Path::new(&dir);
-    let mut mt = term::stdout().expect("Could not unwrap term::stdout.");
+        min_depth,
+        count_below,
+        show_truncated,
+        timeout,
+        profile,
+        threads,
+        max_memory,
+        follow_links,
+        target_meta,
+        explain,
+        locale_sort,
+        sort_keys,
+        dirs_first,
+        no_sort,
+        no_config,
+        show_owner,
+        show_group,
+        numeric_ids,
+        show_flags,
+        show_size,
+        bytes,
+        bars,
+        show_date,
+        time_format,
+        time_kind,
+        meta_first,
+        columns,
+        max_name_len,
+        mark_sparse,
+        mark_empty,
+        emoji,
+        show_fs,
+        git_repos,
+        project_badges,
+        group_by_ext,
+        disk_usage,
+        size_kind,
+        inode_usage,
+        report_types,
+        timing,
+        summary_json,
+        report_style,
+        output_format,
+        sqlite,
+        parquet,
+        verify_manifest,
+        traversal,
+        html_base,
+        html_title,
+        no_links,
+        html_intro,
+        html_outro,
+        html_interactive,
+        assert_max_files,
+        assert_max_dirs,
+        assert_max_depth,
+        assert_max_size,
+        fail_if_empty,
+        error_format,
+        no_progress,
+        quiet,
+        copy,
+        command,
+        ..
+    } = args;
+    let charset = if cp437 { Charset::Cp437 } else { charset };
+    let indent = if compact { 2 } else { indent };
+    match command {
+        Some(Command::Compare { old, new }) => {
+            return compare_scans(&old, &new, charset, indent, lang)
+        }
+        Some(Command::Diff { hash, dir, archive }) => {
+            return diff_archive(&dir, &archive, hash, charset, indent, lang)
+        }
+        Some(Command::Generate {
+            depth,
+            files,
+            seed,
+            dir,
+        }) => {
+            let options = GenerateOptions { depth, files, seed };
+            return generate_tree(&dir, &options, lang);
+        }
+        Some(Command::Mkdir { from, dir }) => return create_from(&from, &dir, lang),
+        Some(Command::Pin {
+            remove,
+            dir,
+            options,
+        }) => return pin_dir(&dir, options, remove, lang),
+        Some(Command::Pins { list }) => return list_pins(list, lang),
+        _ => {}
+    }
+    if drives {
+        return print_drives(lang);
+    }
+    if show_flags && !flags::SUPPORTED {
+        return Err(CliError::usage(lang.flags_unsupported()));
+    }
+    let thresholds = Thresholds {
+        max_files: assert_max_files,
+        max_dirs: assert_max_dirs,
+        max_depth: assert_max_depth,
+        max_size: assert_max_size,
+        fail_if_empty,
+    };
+    let include_glob = match include_pattern {
+        Some(pat) => Some(
+            Pattern::new(&pat)
+                .map(Arc::new)
+                .map_err(|err| CliError::usage(lang.invalid_pattern(&pat, err)))?,
+        ),
+        None => None,
+    };
+    let exclude_paths = exclude_paths
+        .iter()
+        .map(std::path::absolute)
+        .collect::<io::Result<Vec<_>>>()
+        .map_err(|err| CliError::failure(lang.execution_failure(err)))?;
+    if let Some(ref format) = time_format {
+        if !is_valid_format(format) {
+            return Err(CliError::usage(lang.invalid_time_format(format)));
+        }
+    }
+    let time_format = time_format.or_else(|| show_date.then(|| DEFAULT_TIME_FORMAT.to_string()));
+    let html = match html_base {
+        Some(base_href) => Some(HtmlOptions {
+            base_href,
+            title: html_title,
+            links: !no_links,
+            intro: read_fragment(html_intro, lang)?,
+            outro: read_fragment(html_outro, lang)?,
+            interactive: html_interactive,
+            indent,
+            max_prefix_depth,
+        }),
+        None => None,
+    };
+    let manifest = match verify_manifest {
+        Some(file) => Some(
+            Manifest::load(&file)
+                .map_err(|err| CliError::usage(lang.root_unreadable(&file, err)))?,
+        ),
+        None => None,
+    };
+    let roots: Vec<PathBuf> = match from_file {
+        Some(file) => {
+            read_roots(&file).map_err(|err| CliError::usage(lang.root_unreadable(&file, err)))?
+        }
+        None => dirs.iter().map(PathBuf::from).collect(),
+    };
+    let (roots, benching) = match command {
+        Some(Command::Bench { dir }) => (vec![dir], true),
+        _ => (roots, false),
+    };
+    if roots.len() > 1 && (html.is_some() || output_format.is_some() || manifest.is_some()) {
+        return Err(CliError::usage(lang.single_root_only()));
+    }
+    for path in &roots {
+        path.symlink_metadata()
+            .map_err(|err| CliError::failure(lang.root_unreadable(path, err)))?;
+    }
+    let project = match find_config(&roots[0]).filter(|_| !no_config) {
+        Some(file) => ProjectConfig::load(&file)
+            .map_err(|err| CliError::usage(lang.root_unreadable(&file, err)))?,
+        None => ProjectConfig::default(),
+    };
+    // 项目配置只提供默认值，命令行上的 -L 优先
+    let max_level = if max_level == usize::MAX {
+        project.level.unwrap_or(max_level)
+    } else {
+        max_level
+    };
+    if max_level == 0 {
+        return Err(CliError::usage(lang.invalid_level()));
+    }
+    if min_depth > max_level {
+        return Err(CliError::usage(
+            lang.invalid_depth_range(min_depth, max_level),
+        ));
+    }
+    let mut color_rules = ColorRules::default();
+    for (pattern, style) in &project.colors {
+        // 不是字符串的值按原样放进错误信息
+        let style = style
+            .as_str()
+            .map_or_else(|| style.to_string(), str::to_string);
+        color_rules
+            .push(pattern, &style)
+            .map_err(|err| CliError::usage(lang.invalid_color_rule(pattern, err)))?;
+    }
+    let dircolors = dircolors
+        .map(|file| {
+            let text = fs::read_to_string(&file)
+                .map_err(|err| CliError::usage(lang.root_unreadable(&file, err)))?;
+            DirColors::parse(&text)
+                .map_err(|err| CliError::usage(lang.invalid_dircolors(&file, err)))
+        })
+        .transpose()?;
+    // 预设放在最前面，项目配置和命令行中的 `!` 规则可以把条目找回来
+    let presets = if no_macos_noise { MACOS_NOISE } else { &[] };
+    let exclude_glob = presets
+        .iter()
+        .copied()
+        .chain(project.exclude.iter().map(String::as_str))
+        .chain(exclude_pattern.iter().map(String::as_str))
+        .map(|pat| Pattern::new(pat).map_err(|err| CliError::usage(lang.invalid_pattern(pat, err))))
+        .collect::<Result<Vec<_>, _>>()?;
+    let exclude_glob = Arc::new(ExcludeRules::new(exclude_glob));
+    let tracked = if tracked {
+        let mut tracked = TrackedFiles::default();
+        for path in &roots {
+            let files =
+                tracked_files(path).map_err(|err| CliError::failure(lang.git_failed(err)))?;
+            tracked.extend(files);
+        }
+        Some(tracked)
+    } else {
+        None
+    };
+    // 单独给出 --disk-usage 或 --bytes 时显示每个条目的大小
+    let show_size =
+        show_size || ((size_kind != SizeKind::Apparent || bytes.is_some()) && !disk_usage);
+    let network = match profile {
+        ScanProfile::Auto => {
+            let mounts = MountTable::load();
+            roots.iter().any(|root| mounts.is_network(root))
+        }
+        ScanProfile::Local => false,
+        ScanProfile::Network => true,
+    };
+    let mut mt = Terminal::stdout();
     let config = Config {
-        colorful: color_on || !color_off,
-        show_all,
-        max_level,
-        include_glob: include_pattern.map(|pat| {
-            Glob::new(pat.as_str())
-                .expect("include_pattern is not valid")
-                .compile_matcher()
+        traversal: TraversalOptions {
+            max_level,
+            follow_links,
+            locale_sort,
+            sort_keys,
+            // 配置文件中的 dirsfirst 也不再适用
+            dirs_first: !no_sort && (dirs_first || project.dirsfirst),
+            unsorted: no_sort,
+            count_below,
+            show_truncated,
+            // 需要显示元数据时，在网络文件系统上逐个 stat 是主要的耗时；明确给出线程数时按它来
+            parallel_stat: match threads {
+                Some(threads) => threads > 1,
+                None => network || show_size || disk_usage || time_format.is_some(),
+            },
+            threads: threads.unwrap_or_else(default_threads),
+            timeout: timeout.or(network.then_some(NETWORK_TIMEOUT)),
+        },
+        filter: FilterOptions {
+            show_all: show_all || project.all,
+            hidden_only,
+            include_glob,
+            exclude_glob,
+            exclude_paths,
+            empty_dirs_only,
+            min_depth,
+            tracked,
+        },
+        display: DisplayOptions {
+            colorful: color_on || (!color_off && mt.supports_color()),
+            age_colors,
+            size_colors,
+            color_rules,
+            dircolors,
+            charset,
+            indent,
+            max_prefix_depth,
+            show_size,
+            size_kind,
+            byte_format: bytes.unwrap_or_default(),
+            inode_usage,
+            show_owner,
+            show_group,
+            numeric_ids,
+            show_flags,
+            bars,
+            target_meta,
+            time_format,
+            time_kind,
+            meta_first,
+            columns: columns.then(|| mt.width()),
+            max_name_len,
+            mark_sparse,
+            mark_empty,
+            emoji: emoji || project.emoji,
+            show_fs,
+            git_repos,
+            project_badges,
+            group_by_ext,
+            disk_usage,
+            copy,
+            html,
+            output_format,
+            lang,
+        },
+    };
+    if let Some(path) = explain {
+        // 相对路径按列表中显示的方式，相对第一个根目录解释
+        let path = roots[0].join(path);
+        let iterator = FileIterator::new(&roots[0], &config.traversal, &config.filter);
+        let (decided_by, verdict) = iterator.explain(&path);
+        return writeln!(mt, "{}", lang.explain(&path, &decided_by, &verdict))
+            .and_then(|_| mt.flush())
+            .map_err(|err| CliError::output(lang, err));
+    }
+    if benching {
+        let report = bench(&roots[0], &config).map_err(|err| CliError::output(lang, err))?;
+        return writeln!(mt, "{}", lang.bench_report(&report))
+            .and_then(|_| mt.flush())
+            .map_err(|err| CliError::output(lang, err));
+    }
+    let report_for = |summary: &DirSummary| {
+        if quiet {
+            return None;
+        }
+        let (total, allocated) = match size_kind {
+            SizeKind::Apparent => (summary.total_size, None),
+            SizeKind::Allocated => (summary.total_allocated, None),
+            SizeKind::Both => (summary.total_size, Some(summary.total_allocated)),
+        };
+        let byte_format = bytes.unwrap_or_default();
+        let total_size = |size| match bytes {
+            Some(format) => format!("{} B", format.format(size)),
+            None => human_size(size),
+        };
+        let counts = lang.report(summary.num_folders, summary.num_files, summary.num_symlinks);
+        let mut report = if disk_usage {
+            let mut report = lang.report_with_size(counts, &byte_format.format(total));
+            if let Some(allocated) = allocated {
+                report.push_str(&lang.allocated_total(&byte_format.format(allocated)));
+            }
+            report
+        } else if show_size {
+            let mut report = lang.report_with_total(counts, &total_size(total));
+            if let Some(allocated) = allocated {
+                report.push_str(&lang.allocated_total(&total_size(allocated)));
+            }
+            report
+        } else {
+            counts
+        };
+        if inode_usage {
+            report.push_str(&lang.inodes_used(summary.total_inodes));
+        }
+        if report_style == ReportStyle::Long {
+            report.push('\n');
+            report.push_str(&lang.report_details(summary));
+        }
+        if report_types {
+            report.push('\n');
+            report.push_str(&lang.report_types(&summary.kinds));
+        }
+        Some(report)
+    };
+    if let Some(file) = from_json {
+        let scan =
+            Scan::load(&file).map_err(|err| CliError::usage(lang.root_unreadable(&file, err)))?;
+        let summary =
+            render_scan(&scan, &config, &mut mt).map_err(|err| CliError::output(lang, err))?;
+        if let Some(report) = report_for(&summary) {
+            writeln!(mt, "\n{}", report).map_err(|err| CliError::output(lang, err))?;
+        }
+        mt.flush().map_err(|err| CliError::output(lang, err))?;
+        return check_thresholds(&thresholds, &summary, lang);
+    }
+    let started = Instant::now();
+    // 计时和报告一样算作附加信息，`--quiet` 时不输出
+    let timing = timing && !quiet;
+    let io_before = timing.then(IoCounters::current).flatten();
+    let mut progress = Progress::new(lang, !no_progress && !quiet);
+    if network {
+        progress = progress.immediate();
+    }
+    let mut trees: Vec<Tree> = Vec::new();
+    // 超出 --max-memory 的根目录及其后的根目录边读取边输出
+    let mut streams: Vec<StreamingScan> = Vec::new();
+    let mut budget = max_memory.map_or(usize::MAX, |megabytes| megabytes.saturating_mul(1 << 20));
+    for path in &roots {
+        if !streams.is_empty() {
+            streams.push(StreamingScan::new(path, &config));
+            continue;
+        }
+        match build_tree_within(path, &config, budget, &mut || progress.tick()) {
+            Ok(tree) => {
+                if max_memory.is_some() {
+                    budget = budget.saturating_sub(tree.footprint());
+                }
+                trees.push(tree);
+            }
+            Err(stream) => streams.push(*stream),
+        }
+    }
+    progress.finish();
+    let json_errors = error_format == ErrorFormat::Json;
+    if json_errors {
+        print_json_errors(trees.iter().flat_map(traversal_errors));
+    }
+    let mut summary = DirSummary::init();
+    let mut scan = ScanTimings::default();
+    for tree in &trees {
+        summary.merge(&tree.summary);
+        scan.merge(&tree.timings);
+    }
+    // 在输出之后调用，耗时包含渲染
+    let timing_report = |summary: &DirSummary, scan: ScanTimings| {
+        let report = TimingReport {
+            elapsed: started.elapsed(),
+            entries: summary.num_folders + summary.num_files + summary.num_symlinks,
+            scan,
+            io: io_before.and_then(|before| Some(IoCounters::current()?.since(&before))),
+        };
+        lang.timing_report(&report)
+    };
+
+    if let Some(ref file) = sqlite {
+        write_sqlite(&trees, file, traversal)
+            .map_err(|err| CliError::failure(lang.export_failed(file, err)))?;
+    }
+    if let Some(ref file) = parquet {
+        write_parquet(&trees, file, traversal)
+            .map_err(|err| CliError::failure(lang.export_failed(file, err)))?;
+    }
+
+    if summary_json {
+        let json = export::summary_json(&roots, &summary);
+        write!(mt, "{}", json)
+            .and_then(|_| mt.flush())
+            .map_err(|err| CliError::output(lang, err))?;
+        if timing {
+            eprintln!("{}", timing_report(&summary, scan));
+        }
+        return check_thresholds(&thresholds, &summary, lang);
+    }
+
+    if let Some(format) = config.display.output_format {
+        let tree = &trees[0];
+        let rows = format.render(tree, config.display.time_format.as_deref(), traversal);
+        write!(mt, "{}", rows)
+            .and_then(|_| mt.flush())
+            .map_err(|err| CliError::output(lang, err))?;
+        if copy {
+            copy_page(&rows, lang, quiet);
+        }
+        if timing {
+            eprintln!("{}", timing_report(&summary, scan));
+        }
+        return check_thresholds(&thresholds, &summary, lang);
+    }
+
+    if let Some(ref html) = config.display.html {
+        let page = render_html(&trees[0], html, report_for(&summary).as_deref());
+        write!(mt, "{}", page)
+            .and_then(|_| mt.flush())
+            .map_err(|err| CliError::output(lang, err))?;
+        if copy {
+            copy_page(&page, lang, quiet);
+        }
+        if timing {
+            eprintln!("{}", timing_report(&summary, scan));
+        }
+        return check_thresholds(&thresholds, &summary, lang);
+    }
+
+    let verification = manifest.map(|manifest| verify(&trees[0], &manifest, &config));
+    let mut renderer = Renderer::new(&config.display, &mut mt);
+    if let Some(ref verification) = verification {
+        renderer = renderer.with_verification(verification);
+    }
+    for tree in &trees {
+        render(tree, &mut renderer).map_err(|err| CliError::output(lang, err))?;
+    }
+    if let (Some(megabytes), false, false) = (max_memory, streams.is_empty(), quiet) {
+        print_error(&lang.memory_limit_reached(megabytes));
+    }
+    for mut stream in streams {
+        // 边读取边输出的条目不在树中，读到时就报告其中的错误
+        let items = stream.by_ref().inspect(|item| {
+            if json_errors {
+                print_json_errors(item_errors(item));
+            }
+        });
+        render_stream(items, &mut renderer).map_err(|err| CliError::output(lang, err))?;
+        let (streamed, timings) = stream.finish();
+        summary.merge(&streamed);
+        scan.merge(&timings);
+    }
+    let copy_buffer = renderer.take_copy_buffer();
+    let report = report_for(&summary).map(|report| format!("\n{}\n", report));
+    if let Some(ref report) = report {
+        write!(mt, "{}", report).map_err(|err| CliError::output(lang, err))?;
+    }
+    if timing {
+        eprintln!("{}", timing_report(&summary, scan));
+    }
+    mt.flush().map_err(|err| CliError::output(lang, err))?;
+
+    if let Some(mut text) = copy_buffer {
+        text.push_str(report.as_deref().unwrap_or_default());
+        copy_page(&text, lang, quiet);
+    }
+    if let Some(verification) = verification {
+        let mismatches = verification.num_mismatches();
+        if mismatches > 0 {
+            return Err(CliError::verification(lang.verify_failed(mismatches)));
+        }
+    }
+    check_thresholds(&thresholds, &summary, lang)
+}
+
+fn compare_scans(
+    old: &Path,
+    new: &Path,
+    charset: Charset,
+    indent: usize,
+    lang: Lang,
+) -> Result<(), CliError> {
+    let load = |file: &Path| {
+        Scan::load(file).map_err(|err| CliError::usage(lang.root_unreadable(file, err)))
+    };
+    let comparison = compare(&load(old)?, &load(new)?);
+    let text = render_changes(&comparison, charset, indent);
+    let (added, removed, modified) = comparison.counts();
+    let mut out = io::stdout().lock();
+    let written = match charset {
+        Charset::Cp437 => out.write_all(&encode_cp437(&text)),
+        _ => out.write_all(text.as_bytes()),
+    };
+    written
+        .and_then(|_| writeln!(out, "\n{}", lang.compare_report(added, removed, modified)))
+        .map_err(|err| CliError::output(lang, err))
+}
+
+fn diff_archive(
+    dir: &Path,
+    archive: &Path,
+    hash: bool,
+    charset: Charset,
+    indent: usize,
+    lang: Lang,
+) -> Result<(), CliError> {
+    if let Err(err) = fs::read_dir(dir) {
+        return Err(CliError::usage(lang.root_unreadable(dir, err)));
+    }
+    // 备份中的内容都要比较，不受隐藏文件和 `-L` 之类选项的影响
+    let mut config = Config::default();
+    config.filter.show_all = true;
+    let tree = build_tree(dir, &config);
+    let comparison = compare_archive(&tree, archive, hash)
+        .map_err(|err| CliError::failure(lang.archive_unreadable(archive, err)))?;
+    let text = render_changes(&comparison, charset, indent);
+    let (added, removed, modified) = comparison.counts();
+    let mut out = io::stdout().lock();
+    let written = match charset {
+        Charset::Cp437 => out.write_all(&encode_cp437(&text)),
+        _ => out.write_all(text.as_bytes()),
+    };
+    written
+        .and_then(|_| writeln!(out, "\n{}", lang.compare_report(added, removed, modified)))
+        .map_err(|err| CliError::output(lang, err))?;
+    if added + removed + modified > 0 {
+        return Err(CliError::verification(lang.archive_differs(dir, archive)));
+    }
+    Ok(())
+}
+
+fn generate_tree(dir: &Path, options: &GenerateOptions, lang: Lang) -> Result<(), CliError> {
+    // 不往已有内容的目录里写，免得和原有的文件混在一起
+    if fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(CliError::usage(lang.generate_target_not_empty(dir)));
+    }
+    let generated =
+        generate(dir, options).map_err(|err| CliError::failure(lang.root_unreadable(dir, err)))?;
+    writeln!(
+        io::stdout(),
+        "{}",
+        lang.generated(dir, generated.dirs, generated.files)
+    )
+    .map_err(|err| CliError::output(lang, err))
+}
+
+fn create_from(file: &Path, dir: &Path, lang: Lang) -> Result<(), CliError> {
+    let text = if file == Path::new("-") {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(file)
+    };
+    let text = text.map_err(|err| CliError::usage(lang.root_unreadable(file, err)))?;
+    let entries =
+        scaffold::parse(&text).map_err(|err| CliError::usage(lang.root_unreadable(file, err)))?;
+    let created = scaffold::create(dir, &entries)
+        .map_err(|err| CliError::failure(lang.root_unreadable(dir, err)))?;
+    writeln!(
+        io::stdout(),
+        "{}",
+        lang.generated(dir, created.dirs, created.files)
+    )
+    .map_err(|err| CliError::output(lang, err))
+}
+
+/// 收藏文件的位置和其中的内容
+fn load_pins(lang: Lang) -> Result<(PathBuf, Pins), CliError> {
+    let file = pins_file().ok_or_else(|| CliError::usage(lang.pins_file_unknown()))?;
+    let pins =
+        Pins::load(&file).map_err(|err| CliError::failure(lang.root_unreadable(&file, err)))?;
+    Ok((file, pins))
+}
+
+/// 按收藏的选项解析列出 `path` 的命令行，`global` 以程序名开头。收藏的选项放在前面，同一个
+/// 选项在命令行上再次给出时以命令行为准，可以重复的 `-E` 等则两边的都生效
+fn pin_args(
+    global: &[OsString],
+    options: &[String],
+    path: &Path,
+    lang: Lang,
+) -> Result<Args, CliError> {
+    let (program, global) = global.split_first().expect("the program name comes first");
+    let args = [program.clone()]
+        .into_iter()
+        .chain(options.iter().map(OsString::from))
+        .chain(global.iter().cloned())
+        .chain([path.as_os_str().to_owned()]);
+    let invalid = |err: &dyn Display| {
+        let message = err.to_string();
+        let first = message.lines().next().unwrap_or_default();
+        CliError::usage(lang.pin_options_invalid(path, first.trim_start_matches("error: ")))
+    };
+    let args = Args::try_parse_from(args).map_err(|err| invalid(&err))?;
+    // 收藏的选项中再有子命令会反复调用自己
+    match args.command {
+        Some(_) => Err(invalid(&lang.pin_subcommand())),
+        None => Ok(args),
+    }
+}
+
+fn pin_dir(dir: &Path, options: Vec<String>, remove: bool, lang: Lang) -> Result<(), CliError> {
+    let (file, mut pins) = load_pins(lang)?;
+    let path =
+        std::path::absolute(dir).map_err(|err| CliError::usage(lang.root_unreadable(dir, err)))?;
+    let message = if remove {
+        if !pins.unpin(&path) {
+            return Err(CliError::usage(lang.not_pinned(&path)));
+        }
+        lang.unpinned(&path)
+    } else {
+        if !path.is_dir() {
+            let err = fs::metadata(&path)
+                .err()
+                .unwrap_or_else(|| io::ErrorKind::NotADirectory.into());
+            return Err(CliError::usage(lang.root_unreadable(dir, err)));
+        }
+        pin_args(&[OsString::from("tree-cli")], &options, &path, lang)?;
+        pins.pin(path.clone(), options);
+        lang.pinned(&path)
+    };
+    pins.save(&file)
+        .map_err(|err| CliError::failure(lang.root_unreadable(&file, err)))?;
+    writeln!(io::stdout(), "{}", message).map_err(|err| CliError::output(lang, err))
+}
+
+/// `tree-cli pins`：依次列出收藏的目录。某个目录出错时打印出来，继续列出其余的目录
+fn list_pins(list: bool, lang: Lang) -> Result<(), CliError> {
+    let (_, pins) = load_pins(lang)?;
+    let mut out = io::stdout();
+    if pins.pins.is_empty() {
+        return writeln!(out, "{}", lang.no_pins()).map_err(|err| CliError::output(lang, err));
+    }
+    if list {
+        for pin in &pins.pins {
+            let line = std::iter::once(pin.path.display().to_string())
+                .chain(pin.options.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(out, "{}", line).map_err(|err| CliError::output(lang, err))?;
+        }
+        return Ok(());
+    }
+    // 不带 `--list` 时 `pins` 是最后一个参数，之前的选项用于每个目录
+    let mut global: Vec<OsString> = env::args_os().collect();
+    global.pop();
+    let mut failed = None;
+    for (index, pin) in pins.pins.iter().enumerate() {
+        if index > 0 {
+            writeln!(out).map_err(|err| CliError::output(lang, err))?;
+        }
+        match pin_args(&global, &pin.options, &pin.path, lang).and_then(|args| run(args, lang)) {
+            // 输出管道已经关闭
+            Err(err) if err.message.is_empty() => return Err(err),
+            Err(err) => {
+                print_error(&err.message);
+                failed.get_or_insert(err.code);
+            }
+            Ok(()) => {}
+        }
+    }
+    match failed {
+        Some(code) => Err(CliError {
+            code,
+            message: String::new(),
         }),
+        None => Ok(()),
+    }
+}
+
+fn print_drives(lang: Lang) -> Result<(), CliError> {
+    let drives = logical_drives();
+    if drives.is_empty() {
+        return Err(CliError::usage(lang.drives_unsupported()));
+    }
+    let mut out = io::stdout().lock();
+    for drive in drives {
+        writeln!(out, "{}", drive.display()).map_err(|err| CliError::output(lang, err))?;
+    }
+    Ok(())
+}
+
+fn check_thresholds(
+    thresholds: &Thresholds,
+    summary: &DirSummary,
+    lang: Lang,
+) -> Result<(), CliError> {
+    if thresholds.is_empty() {
+        return Ok(());
+    }
+    if thresholds.is_unmet(summary) {
+        return Err(CliError::empty(lang.no_files_listed()));
+    }
+    let violations = thresholds.check(summary);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::assertion(violations))
+    }
+}
+
+/// `--errors json`：每个错误在 stderr 上输出一行 JSON
+fn print_json_errors(errors: impl IntoIterator<Item = TraversalError>) {
+    let mut err = io::stderr().lock();
+    for error in errors {
+        let line = serde_json::to_string(&error).expect("errors are serializable");
+        let _ = writeln!(err, "{}", line);
+    }
+}
+
+fn copy_page(text: &str, lang: Lang, quiet: bool) {
+    if let Err(err) = copy_to_clipboard(text) {
+        if !quiet {
+            print_error(&lang.clipboard_failed(err));
+        }
+    }
+}
+
+/// 从文件（`-` 表示标准输入）中读取要列出的目录，每行一个，忽略空行
+fn read_roots(file: &Path) -> io::Result<Vec<PathBuf>> {
+    let text = if file == Path::new("-") {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(file)?
     };
-    let mut dir_tree = DirTree::new(config, &mut mt);
-    let DirSummary {
-        num_folders,
-        num_files,
-    } = dir_tree.print_folders(&path).expect("execution failure");
+    Ok(text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// `--timeout` 的参数，允许小数秒
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .filter(|timeout| !timeout.is_zero())
+        .ok_or_else(|| format!("`{}` is not a positive number of seconds", value))
+}
 
-    writeln!(mt, "\n{} directories, {} files", num_folders, num_files).unwrap()
+/// 读取 HTML 开头或结尾片段文件
+fn read_fragment(file: Option<PathBuf>, lang: Lang) -> Result<Option<String>, CliError> {
+    match file {
+        Some(file) => fs::read_to_string(&file)
+            .map(Some)
+            .map_err(|err| CliError::usage(lang.root_unreadable(&file, err))),
+        None => Ok(None),
+    }
 }
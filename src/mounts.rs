@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+
+/// 网络文件系统和 FUSE 文件系统的类型名；`fuseblk` 通常是本地磁盘上的 NTFS，不算在内
+const NETWORK_FS_TYPES: [&str; 12] = [
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "lustre",
+    "davfs",
+    "fuse",
+];
+
+/// 遍历时的策略，`--profile`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ScanProfile {
+    /// Use the network profile when a root is on a network or FUSE filesystem
+    #[default]
+    Auto,
+    /// Settings for local disks
+    Local,
+    /// Stat entries in parallel, show progress right away and give up on slow operations
+    Network,
+}
+
+/// 挂载点及其文件系统类型，用于 `--show-fs`
+#[derive(Debug, Default)]
+pub struct MountTable {
+    mounts: Vec<(PathBuf, String)>,
+}
+
+impl MountTable {
+    /// Read the mount table of the current process. Only Linux exposes one that can be parsed
+    /// without extra dependencies; elsewhere the table is empty and types are unknown.
+    #[cfg(target_os = "linux")]
+    pub fn load() -> MountTable {
+        let text = std::fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+        MountTable::parse(&text)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn load() -> MountTable {
+        MountTable::default()
+    }
+
+    /// 解析 `/proc/self/mounts` 格式：设备 挂载点 类型 选项 ...
+    pub fn parse(text: &str) -> MountTable {
+        let mounts = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let _device = fields.next()?;
+                let mount_point = unescape(fields.next()?);
+                let fs_type = fields.next()?.to_string();
+                Some((PathBuf::from(mount_point), fs_type))
+            })
+            .collect();
+        MountTable { mounts }
+    }
+
+    /// The type of the filesystem `path` lives on, from the longest matching mount point.
+    pub fn fs_type(&self, path: &Path) -> Option<&str> {
+        let path = path.canonicalize().ok()?;
+        // 同一挂载点被多次挂载时以最后一次为准，max_by_key 正好返回最后一个最大值
+        self.mounts
+            .iter()
+            .filter(|(mount_point, _)| path.starts_with(mount_point))
+            .max_by_key(|(mount_point, _)| mount_point.components().count())
+            .map(|(_, fs_type)| fs_type.as_str())
+    }
+
+    /// Whether `path` is on a network filesystem (NFS, SMB, ...) or a FUSE mount, where every
+    /// operation may be a round trip to another machine.
+    pub fn is_network(&self, path: &Path) -> bool {
+        self.fs_type(path).is_some_and(|fs_type| {
+            NETWORK_FS_TYPES.contains(&fs_type) || fs_type.starts_with("fuse.")
+        })
+    }
+}
+
+/// 挂载点中的空格等字符被写成 `\040` 这样的八进制转义
+fn unescape(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(index) = rest.find('\\') {
+        result.push_str(&rest[..index]);
+        let code = rest.get(index + 1..index + 4);
+        match code.and_then(|code| u8::from_str_radix(code, 8).ok()) {
+            Some(byte) => {
+                result.push(byte as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                result.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,116 @@
+use std::fmt::Write;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use sha2::{Digest, Sha256};
+
+use crate::core::{Node, Traversal, Tree};
+use crate::symbol::{special_kind, SpecialKind};
+
+/// Render the tree as a BSD mtree specification in the full-path form written by
+/// `bsdtar --format=mtree`: one line per entry with `type`, `mode`, `size`, `time`, `link`
+/// and `sha256digest` keywords, paths relative to the root as `./...`.
+pub fn render_mtree(tree: &Tree, _time_format: Option<&str>, traversal: Traversal) -> String {
+    let mut output = String::from("#mtree\n");
+    let root = &tree.root.item.path();
+    for node in tree.nodes(traversal) {
+        mtree_node(node, root, &mut output);
+    }
+    output
+}
+
+fn mtree_node(node: &Node, root: &Path, output: &mut String) {
+    let item = &node.item;
+    // 读取失败的条目没有可以写出的属性
+    if let Ok(ref metadata) = item.metadata {
+        let path = item.path();
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        output.push('.');
+        for component in relative.components() {
+            output.push('/');
+            encode(component.as_os_str().as_encoded_bytes(), output);
+        }
+        write_keywords(&path, metadata, output);
+        output.push('\n');
+    }
+}
+
+fn write_keywords(path: &Path, metadata: &Metadata, output: &mut String) {
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_symlink() {
+        "link"
+    } else if file_type.is_dir() {
+        "dir"
+    } else {
+        match special_kind(metadata) {
+            Some(SpecialKind::Fifo) => "fifo",
+            Some(SpecialKind::Socket) => "socket",
+            Some(SpecialKind::CharDevice) => "char",
+            Some(SpecialKind::BlockDevice) => "block",
+            None => "file",
+        }
+    };
+    let _ = write!(output, " type={}", kind);
+    if let Some(mode) = permissions(metadata) {
+        let _ = write!(output, " mode={:04o}", mode);
+    }
+    if let Ok(time) = metadata.modified() {
+        if let Ok(since_epoch) = time.duration_since(UNIX_EPOCH) {
+            let _ = write!(
+                output,
+                " time={}.{:09}",
+                since_epoch.as_secs(),
+                since_epoch.subsec_nanos()
+            );
+        }
+    }
+    if file_type.is_symlink() {
+        if let Ok(target) = fs::read_link(path) {
+            output.push_str(" link=");
+            encode(target.as_os_str().as_encoded_bytes(), output);
+        }
+    } else if file_type.is_file() {
+        let _ = write!(output, " size={}", metadata.len());
+        // 无法读取内容时只是没有摘要，校验工具会报告缺少的关键字
+        if let Ok(digest) = File::open(path).and_then(sha256) {
+            let _ = write!(output, " sha256digest={}", digest);
+        }
+    }
+}
+
+/// The SHA-256 digest of everything `reader` yields, as lowercase hex.
+pub fn sha256(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        }))
+}
+
+#[cfg(unix)]
+fn permissions(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn permissions(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+/// mtree 的名称中空白、`#`、`\` 和不可打印的字节写成三位八进制转义
+fn encode(bytes: &[u8], output: &mut String) {
+    for &byte in bytes {
+        if byte.is_ascii_graphic() && byte != b'\\' && byte != b'#' {
+            output.push(byte as char);
+        } else {
+            let _ = write!(output, "\\{:03o}", byte);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+
+/// User and group names read from `/etc/passwd` and `/etc/group`. Ids without an entry, or
+/// all of them when the files are missing (minimal containers), are shown as numbers.
+#[derive(Debug, Default)]
+pub struct IdNames {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl IdNames {
+    pub fn load() -> IdNames {
+        let read = |path| fs::read_to_string(path).map_or_else(|_| HashMap::new(), |t| parse(&t));
+        IdNames {
+            users: read("/etc/passwd"),
+            groups: read("/etc/group"),
+        }
+    }
+
+    pub fn user(&self, uid: u32) -> String {
+        self.users
+            .get(&uid)
+            .cloned()
+            .unwrap_or_else(|| uid.to_string())
+    }
+
+    pub fn group(&self, gid: u32) -> String {
+        self.groups
+            .get(&gid)
+            .cloned()
+            .unwrap_or_else(|| gid.to_string())
+    }
+}
+
+/// `name:password:id:...` 格式，同一个 id 取第一个名字
+fn parse(text: &str) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        let mut fields = line.split(':');
+        let (Some(name), Some(_), Some(id)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if let Ok(id) = id.parse() {
+            names.entry(id).or_insert_with(|| name.to_string());
+        }
+    }
+    names
+}
+
+/// 条目的 (uid, gid)，没有这一概念的平台返回 `None`
+#[cfg(unix)]
+pub fn owner_ids(metadata: &Metadata) -> Option<(u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.uid(), metadata.gid()))
+}
+
+#[cfg(not(unix))]
+pub fn owner_ids(_metadata: &Metadata) -> Option<(u32, u32)> {
+    None
+}
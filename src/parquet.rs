@@ -0,0 +1,126 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use ::parquet::basic::Compression;
+use ::parquet::data_type::{ByteArray, ByteArrayType, DataType, Int32Type, Int64Type};
+use ::parquet::errors::Result;
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+use ::parquet::schema::parser::parse_message_type;
+
+use crate::core::{Traversal, Tree};
+use crate::export::entry_kind;
+use crate::file_iterator::{escape_name, FileItem};
+
+const SCHEMA: &str = "message entries {
+    REQUIRED BYTE_ARRAY path (UTF8);
+    OPTIONAL BYTE_ARRAY parent (UTF8);
+    REQUIRED BYTE_ARRAY type (UTF8);
+    REQUIRED INT64 size;
+    OPTIONAL INT64 mtime;
+    REQUIRED INT32 depth;
+}";
+
+/// 每个行组的条目数，避免读取时一次载入整个文件
+const ROW_GROUP_SIZE: usize = 1 << 20;
+
+/// Write every entry of `trees` to `file` in Apache Parquet format, with the same columns as
+/// the SQLite export: path, parent, type, size, mtime (seconds since the Unix epoch) and depth.
+/// Columns are compressed with Snappy. Rows are written in the order of `traversal`.
+pub fn write_parquet(trees: &[Tree], file: &Path, traversal: Traversal) -> io::Result<()> {
+    let items: Vec<&FileItem> = trees
+        .iter()
+        .flat_map(|tree| tree.nodes(traversal))
+        .map(|node| &node.item)
+        .collect();
+    write_items(&items, File::create(file)?).map_err(io::Error::other)
+}
+
+fn write_items(items: &[&FileItem], file: File) -> Result<()> {
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = SerializedFileWriter::new(file, schema, Arc::new(properties))?;
+    for chunk in items.chunks(ROW_GROUP_SIZE) {
+        let mut row_group = writer.next_row_group()?;
+        write_row_group(chunk, &mut row_group)?;
+        row_group.close()?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+fn write_row_group(
+    items: &[&FileItem],
+    row_group: &mut SerializedRowGroupWriter<File>,
+) -> Result<()> {
+    let paths: Vec<ByteArray> = items
+        .iter()
+        .map(|item| ByteArray::from(escape_name(item.path().as_os_str()).as_ref()))
+        .collect();
+    write_column::<ByteArrayType>(row_group, &paths, None)?;
+
+    // 根目录没有 parent，用定义级别 0 表示 NULL
+    let parents: Vec<Option<ByteArray>> = items
+        .iter()
+        .map(|item| {
+            item.path()
+                .parent()
+                .filter(|_| item.level > 0)
+                .map(|p| ByteArray::from(escape_name(p.as_os_str()).as_ref()))
+        })
+        .collect();
+    let (values, levels) = flatten(parents);
+    write_column::<ByteArrayType>(row_group, &values, Some(&levels))?;
+
+    let kinds: Vec<ByteArray> = items
+        .iter()
+        .map(|item| ByteArray::from(entry_kind(item)))
+        .collect();
+    write_column::<ByteArrayType>(row_group, &kinds, None)?;
+
+    let sizes: Vec<i64> = items
+        .iter()
+        .map(|item| item.metadata.as_ref().ok().map_or(0, |m| m.len() as i64))
+        .collect();
+    write_column::<Int64Type>(row_group, &sizes, None)?;
+
+    let mtimes: Vec<Option<i64>> = items
+        .iter()
+        .map(|item| {
+            item.metadata
+                .as_ref()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+        })
+        .collect();
+    let (values, levels) = flatten(mtimes);
+    write_column::<Int64Type>(row_group, &values, Some(&levels))?;
+
+    let depths: Vec<i32> = items.iter().map(|item| item.level as i32).collect();
+    write_column::<Int32Type>(row_group, &depths, None)
+}
+
+/// 可空列只写入非空值，另外给出每一行的定义级别
+fn flatten<T>(values: Vec<Option<T>>) -> (Vec<T>, Vec<i16>) {
+    let levels = values.iter().map(|v| v.is_some() as i16).collect();
+    (values.into_iter().flatten().collect(), levels)
+}
+
+fn write_column<T: DataType>(
+    row_group: &mut SerializedRowGroupWriter<File>,
+    values: &[T::T],
+    levels: Option<&[i16]>,
+) -> Result<()> {
+    let mut column = row_group
+        .next_column()?
+        .expect("the schema has a column for every write");
+    column.typed::<T>().write_batch(values, levels, None)?;
+    column.close()
+}
@@ -0,0 +1,120 @@
+use std::ffi::OsStr;
+use std::path::{Component, Path};
+
+use globset::{Glob, GlobBuilder, GlobMatcher};
+
+/// `--no-macos-noise` 排除的条目：Finder 元数据、Spotlight 索引、回收站、压缩包里的资源分支
+pub const MACOS_NOISE: &[&str] = &[
+    ".DS_Store",
+    ".Spotlight-V100",
+    ".Trashes",
+    "__MACOSX",
+    "._*",
+];
+
+/// A `-P` or `-E` pattern with globset syntax: `*`, `?`, `[a-z]`, `[!a-z]`, `{a,b}` and `**`.
+/// A pattern without `/` is matched against the file name; one with `/` is matched against the
+/// path relative to the root, where `*` stays within one component and `**` crosses them.
+///
+/// As in `.gitignore`, a trailing `/` restricts the pattern to directories (`build/`) and a
+/// leading `/` anchors it at the root (`/build` matches `build` but not `src/build`).
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    matcher: GlobMatcher,
+    /// 以 `!` 开头：`-P` 中表示列出不匹配的文件，`-E` 中表示重新包含
+    negated: bool,
+    match_path: bool,
+    /// 以 `/` 结尾，只匹配目录
+    dir_only: bool,
+}
+
+impl Pattern {
+    pub fn new(spec: &str) -> Result<Pattern, globset::Error> {
+        let (glob, negated) = match spec.strip_prefix('!') {
+            Some(rest) => (rest, true),
+            None => (spec, false),
+        };
+        let (glob, dir_only) = match glob.strip_suffix('/') {
+            Some(rest) if !rest.is_empty() => (rest, true),
+            _ => (glob, false),
+        };
+        // 开头的 `/` 只表示从根目录算起，相对路径中没有它
+        let (glob, anchored) = match glob.strip_prefix('/') {
+            Some(rest) if !rest.is_empty() => (rest, true),
+            _ => (glob, false),
+        };
+        let matcher = GlobBuilder::new(glob)
+            .literal_separator(true)
+            .build()?
+            .compile_matcher();
+        Ok(Pattern {
+            source: spec.to_string(),
+            matcher,
+            negated,
+            match_path: anchored || glob.contains('/'),
+            dir_only,
+        })
+    }
+
+    /// 不考虑 `!` 的匹配结果，`relative` 是条目相对根目录的路径
+    pub fn matches(&self, name: &OsStr, relative: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            false
+        } else if self.match_path {
+            self.matcher.is_match(relative)
+        } else {
+            self.matcher.is_match(name)
+        }
+    }
+
+    /// 考虑 `!` 之后的匹配结果，用于 `-P`
+    pub fn is_match(&self, name: &OsStr, relative: &Path, is_dir: bool) -> bool {
+        self.matches(name, relative, is_dir) != self.negated
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// 编译前的 glob，不含 `!`，用于把多条规则合并成一个 `GlobSet`
+    pub fn glob(&self) -> &Glob {
+        self.matcher.glob()
+    }
+
+    /// 含 `/` 的模式匹配相对路径，否则匹配文件名
+    pub fn matches_path(&self) -> bool {
+        self.match_path
+    }
+
+    /// 以 `/` 结尾的模式只匹配目录
+    pub fn is_dir_only(&self) -> bool {
+        self.dir_only
+    }
+
+    /// Whether this path pattern could match something strictly inside the directory `dir`
+    /// (relative to the root). Name patterns never look inside directories that are excluded.
+    pub fn may_match_below(&self, dir: &Path) -> bool {
+        if !self.match_path {
+            return false;
+        }
+        let glob = self.matcher.glob().glob();
+        let mut parts = glob.split('/');
+        for component in dir.components() {
+            let Component::Normal(name) = component else {
+                continue;
+            };
+            match parts.next() {
+                // 含通配符的部分可能匹配任何名字
+                Some(part) if part.contains(['*', '?', '[', '{']) => return true,
+                Some(part) if OsStr::new(part) == name => {}
+                _ => return false,
+            }
+        }
+        parts.next().is_some()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+use serde::{Deserialize, Serialize};
+
+/// 状态目录下保存收藏目录的文件
+const PINS_FILE: &str = "pins.toml";
+
+/// A directory pinned with `tree-cli pin`, listed again by `tree-cli pins` with its options.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pin {
+    pub path: PathBuf,
+    /// 列出时放在目录前面的命令行选项，例如 `["-L", "2"]`
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// The pinned directories, in the order they were first pinned.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pins {
+    /// 文件中写作 `[[pin]]`
+    #[serde(rename = "pin")]
+    pub pins: Vec<Pin>,
+}
+
+impl Pins {
+    /// Read the pins saved in `file`; a missing file means nothing is pinned yet.
+    pub fn load(file: &Path) -> io::Result<Pins> {
+        match fs::read_to_string(file) {
+            Ok(text) => toml::from_str(&text).map_err(io::Error::other),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Pins::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Write the pins to `file`, creating its directory if needed.
+    pub fn save(&self, file: &Path) -> io::Result<()> {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        // 先写到临时文件再改名，写到一半中断时不会丢掉原有的收藏
+        let temp = file.with_extension("toml.tmp");
+        fs::write(&temp, text)?;
+        fs::rename(&temp, file)
+    }
+
+    /// Pin `path`, or replace the options of the pin it already has.
+    pub fn pin(&mut self, path: PathBuf, options: Vec<String>) {
+        match self.pins.iter_mut().find(|pin| pin.path == path) {
+            Some(pin) => pin.options = options,
+            None => self.pins.push(Pin { path, options }),
+        }
+    }
+
+    /// Remove the pin of `path`, returning whether there was one.
+    pub fn unpin(&mut self, path: &Path) -> bool {
+        let before = self.pins.len();
+        self.pins.retain(|pin| pin.path != path);
+        self.pins.len() < before
+    }
+}
+
+/// Where the pins are kept: `$TREE_CLI_PINS` if set, otherwise `tree-cli/pins.toml` in the
+/// platform's directory for application state (`$XDG_STATE_HOME` or `~/.local/state` on
+/// Linux, `~/Library/Application Support` on macOS, `%LOCALAPPDATA%` on Windows).
+pub fn pins_file() -> Option<PathBuf> {
+    if let Some(file) = env::var_os("TREE_CLI_PINS").filter(|file| !file.is_empty()) {
+        return Some(PathBuf::from(file));
+    }
+    state_dir().map(|dir| dir.join("tree-cli").join(PINS_FILE))
+}
+
+#[cfg(windows)]
+fn state_dir() -> Option<PathBuf> {
+    env::var_os("LOCALAPPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn state_dir() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn state_dir() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn state_dir() -> Option<PathBuf> {
+    None
+}
@@ -0,0 +1,72 @@
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use crate::i18n::Lang;
+
+/// 扫描超过这个时间才开始显示进度
+const DELAY: Duration = Duration::from_secs(1);
+/// 两次刷新之间的最短间隔
+const INTERVAL: Duration = Duration::from_millis(100);
+/// 每读取这么多条目才检查一次时间
+const CHECK_EVERY: usize = 64;
+const FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
+/// A spinner with an entry count on stderr, shown only once a scan has run for a while.
+pub struct Progress {
+    lang: Lang,
+    enabled: bool,
+    /// 开始显示之前等待的时间
+    delay: Duration,
+    started: Instant,
+    last_draw: Option<Instant>,
+    scanned: usize,
+    frame: usize,
+}
+
+impl Progress {
+    /// 标准错误不是终端时不显示
+    pub fn new(lang: Lang, enabled: bool) -> Progress {
+        Progress {
+            lang,
+            enabled: enabled && io::stderr().is_terminal(),
+            delay: DELAY,
+            started: Instant::now(),
+            last_draw: None,
+            scanned: 0,
+            frame: 0,
+        }
+    }
+
+    /// Start showing progress right away instead of after a second, for scans that are known to
+    /// be slow.
+    pub fn immediate(mut self) -> Progress {
+        self.delay = Duration::ZERO;
+        self
+    }
+
+    pub fn tick(&mut self) {
+        self.scanned += 1;
+        if !self.enabled || !self.scanned.is_multiple_of(CHECK_EVERY) {
+            return;
+        }
+        let now = Instant::now();
+        if now.duration_since(self.started) < self.delay
+            || self
+                .last_draw
+                .is_some_and(|last| now.duration_since(last) < INTERVAL)
+        {
+            return;
+        }
+        self.last_draw = Some(now);
+        self.frame = (self.frame + 1) % FRAMES.len();
+        let line = self.lang.entries_scanned(self.scanned);
+        let _ = write!(io::stderr(), "\r{} {}", FRAMES[self.frame], line);
+    }
+
+    /// 清除进度行，在输出结果之前调用
+    pub fn finish(&mut self) {
+        if self.last_draw.take().is_some() {
+            let _ = write!(io::stderr(), "\r\x1b[K");
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use serde::Deserialize;
+
+/// 项目配置文件的文件名
+pub const CONFIG_FILE: &str = ".tree-cli.toml";
+
+/// Defaults a repository can check in as `.tree-cli.toml`. Options given on the command line
+/// take precedence; `exclude` rules come before any `-E` so that `-E '!…'` can bring entries back.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// 与 `-E` 相同的规则
+    pub exclude: Vec<String>,
+    pub dirsfirst: bool,
+    /// 与 `-a` 相同
+    pub all: bool,
+    /// 没有 `-L` 时使用的层数
+    pub level: Option<usize>,
+    /// 与 `--emoji` 相同
+    pub emoji: bool,
+    /// 名称的 glob 到显示样式，例如 `"*.sql" = "cyan"`，按文件中的顺序保存
+    pub colors: toml::Table,
+}
+
+impl ProjectConfig {
+    pub fn load(path: &Path) -> io::Result<ProjectConfig> {
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(io::Error::other)
+    }
+}
+
+/// Find `.tree-cli.toml` in `root` or the nearest ancestor that has one.
+pub fn find_config(root: &Path) -> Option<PathBuf> {
+    let root = std::path::absolute(root).ok()?;
+    root.ancestors()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|file| file.is_file())
+}
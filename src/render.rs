@@ -0,0 +1,638 @@
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+
+use term::color;
+use unicode_width::UnicodeWidthStr;
+
+use crate::badges::project_badges;
+use crate::core::{Node, Tree};
+use crate::datetime::format_time;
+use crate::file_iterator::{file_id, is_empty_dir, FileItem};
+use crate::flags::{file_flags, flag_names};
+use crate::git::{is_repo_root, repo_status, RepoStatus};
+use crate::mounts::MountTable;
+use crate::owner::{owner_ids, IdNames};
+use crate::size::{allocated_size, SizeKind};
+use crate::symbol::{
+    encode_cp437, is_executable, is_sparse, is_zero_byte, print_path, set_line_prefix,
+    shorten_name, size_bar, special_kind, write_color, Charset, SPACE,
+};
+use crate::terminal::Terminal;
+use crate::verify::{Verification, VerifyStatus};
+use crate::DisplayOptions;
+
+/// `--columns` 时列之间的空格数
+const COLUMN_GAP: usize = 2;
+
+struct ColumnLayout {
+    num_rows: usize,
+    /// 包括列间距在内的列宽
+    column_width: usize,
+    cell_widths: Vec<usize>,
+}
+
+pub struct Renderer<'a> {
+    term: &'a mut Terminal,
+    config: &'a DisplayOptions,
+    copy_buffer: Option<String>,
+    /// 拼接一行的前缀、元数据和文件名，各行重复使用，不用每行重新分配
+    line: String,
+    /// `meta_first` 时元数据列的宽度
+    meta_width: usize,
+    /// `--verify` 的比较结果
+    verification: Option<&'a Verification>,
+    /// `--show-fs` 时使用的挂载表，以及当前路径上每一层目录所在的设备
+    mounts: Option<MountTable>,
+    dir_devices: Vec<Option<u64>>,
+    /// `-u`/`-g` 时用于查找名字，`--numeric-ids` 时为空
+    id_names: Option<IdNames>,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(config: &'a DisplayOptions, term: &'a mut Terminal) -> Renderer<'a> {
+        let copy_buffer = if config.copy {
+            Some(String::new())
+        } else {
+            None
+        };
+        Renderer {
+            term,
+            config,
+            copy_buffer,
+            line: String::new(),
+            meta_width: 0,
+            verification: None,
+            mounts: config.show_fs.then(MountTable::load),
+            dir_devices: Vec::new(),
+            id_names: ((config.show_owner || config.show_group) && !config.numeric_ids)
+                .then(IdNames::load),
+        }
+    }
+
+    /// 在每个条目前标出它是否与清单一致
+    pub fn with_verification(mut self, verification: &'a Verification) -> Renderer<'a> {
+        self.verification = Some(verification);
+        self
+    }
+
+    /// 取出渲染过程中收集的无颜色文本
+    pub fn take_copy_buffer(&mut self) -> Option<String> {
+        self.copy_buffer.take()
+    }
+
+    fn print_line(&mut self, node: &Node, parent_size: u64, prefix: &str) -> io::Result<()> {
+        self.track_device(&node.item);
+        let meta = self.line_meta(node, parent_size);
+        let mut line = std::mem::take(&mut self.line);
+        line.clear();
+        // 元数据默认跟在树形前缀之后，`meta_first` 时放在最前面
+        if self.config.meta_first {
+            let _ = write!(line, "{:<2$}{}", meta, prefix, self.meta_width);
+        } else {
+            line.push_str(prefix);
+            line.push_str(&meta);
+        }
+        self.write_lead(&line)?;
+        self.print_entry(&node.item, &mut line)?;
+        writeln!(self.term)?;
+        if let Some(ref mut buffer) = self.copy_buffer {
+            buffer.push_str(&line);
+            buffer.push('\n');
+        }
+        self.line = line;
+        Ok(())
+    }
+
+    /// `--columns` 时叶子条目的排列方式，放不下两列时返回 `None`
+    fn column_layout(
+        &self,
+        leaves: &[&Node],
+        parent_size: u64,
+        prefix_width: usize,
+    ) -> Option<ColumnLayout> {
+        let width = self.config.columns?;
+        if leaves.len() < 2 {
+            return None;
+        }
+        let cell_widths: Vec<usize> = leaves
+            .iter()
+            .map(|leaf| {
+                self.line_meta(leaf, parent_size).width()
+                    + self.verify_mark(&leaf.item).width()
+                    + self.emoji(&leaf.item).width()
+                    + self.shown_name(&leaf.item).width()
+                    + self.line_suffix(&leaf.item).width()
+            })
+            .collect();
+        let column_width = cell_widths.iter().max().copied().unwrap_or(0) + COLUMN_GAP;
+        let num_columns = (width.saturating_sub(prefix_width) / column_width).min(leaves.len());
+        (num_columns >= 2).then(|| ColumnLayout {
+            num_rows: leaves.len().div_ceil(num_columns),
+            column_width,
+            cell_widths,
+        })
+    }
+
+    /// 按列输出叶子条目，和 `ls` 一样先填满一列再换到下一列
+    fn print_columns(
+        &mut self,
+        leaves: &[&Node],
+        parent_size: u64,
+        prefix: &str,
+        layout: &ColumnLayout,
+    ) -> io::Result<()> {
+        // 第一行接在分支符号后面，之后的行用空格缩进；分支符号与每层缩进一样宽
+        let branch_width = self.config.indent;
+        let keep = prefix.chars().count().saturating_sub(branch_width);
+        let indent: String = prefix
+            .chars()
+            .take(keep)
+            .chain(std::iter::repeat_n(SPACE, branch_width))
+            .collect();
+        for row in 0..layout.num_rows {
+            let lead = if row == 0 { prefix } else { indent.as_str() };
+            self.write_lead(lead)?;
+            let mut line = lead.to_string();
+            let row_cells: Vec<usize> = (row..leaves.len()).step_by(layout.num_rows).collect();
+            for (column, &index) in row_cells.iter().enumerate() {
+                let meta = self.line_meta(leaves[index], parent_size);
+                write!(self.term, "{}", meta)?;
+                line.push_str(&meta);
+                self.print_entry(&leaves[index].item, &mut line)?;
+                if column + 1 < row_cells.len() {
+                    let padding = " ".repeat(layout.column_width - layout.cell_widths[index]);
+                    write!(self.term, "{}", padding)?;
+                    line.push_str(&padding);
+                }
+            }
+            writeln!(self.term)?;
+            if let Some(ref mut buffer) = self.copy_buffer {
+                buffer.push_str(&line);
+                buffer.push('\n');
+            }
+        }
+        Ok(())
+    }
+
+    /// `-L` 截断的目录下代替子条目的说明行
+    fn print_placeholder(&mut self, prefix: &str, num_entries: usize) -> io::Result<()> {
+        let text = self.config.lang.entries_not_shown(num_entries);
+        let lead = if self.config.meta_first {
+            format!("{:<2$}{}", "", prefix, self.meta_width)
+        } else {
+            prefix.to_string()
+        };
+        self.write_lead(&lead)?;
+        writeln!(self.term, "{}", text)?;
+        if let Some(ref mut buffer) = self.copy_buffer {
+            buffer.push_str(&lead);
+            buffer.push_str(&text);
+            buffer.push('\n');
+        }
+        Ok(())
+    }
+
+    /// 输出树形前缀，CP437 时转换成对应的字节
+    fn write_lead(&mut self, lead: &str) -> io::Result<()> {
+        if self.config.charset == Charset::Cp437 {
+            self.term.write_all(&encode_cp437(lead))
+        } else {
+            write!(self.term, "{}", lead)
+        }
+    }
+
+    /// 显示的文件名，`--max-name-len` 时截断
+    fn shown_name<'e>(&self, entry: &'e FileItem) -> Cow<'e, str> {
+        let name = entry.display_name();
+        match self.config.max_name_len {
+            Some(max_len) => match shorten_name(&name, max_len, self.config.charset) {
+                Cow::Owned(short) => Cow::Owned(short),
+                Cow::Borrowed(_) => name,
+            },
+            None => name,
+        }
+    }
+
+    /// 输出校验标记、文件名和附加标记，无颜色的文本追加到 `text`
+    fn print_entry(&mut self, entry: &FileItem, text: &mut String) -> io::Result<()> {
+        let file_name = self.shown_name(entry);
+        let suffix = self.line_suffix(entry);
+        let mark = self.print_verify_mark(entry)?;
+        let emoji = self.emoji(entry);
+        write!(self.term, "{}", emoji)?;
+        if let Ok(ref metadata) = entry.metadata {
+            print_path(&file_name, &entry.path(), metadata, self.term, self.config)?;
+        } else {
+            write!(self.term, "{}", file_name)?;
+        }
+        write!(self.term, "{}", suffix)?;
+        text.push_str(&mark);
+        text.push_str(emoji);
+        text.push_str(&file_name);
+        text.push_str(&suffix);
+        Ok(())
+    }
+
+    /// `--emoji` 时名称前的图标和一个空格，不需要打过补丁的字体
+    fn emoji(&self, entry: &FileItem) -> &'static str {
+        if !self.config.emoji {
+            return "";
+        }
+        match entry.metadata {
+            _ if entry.is_symlink && !entry.is_dir() => "🔗 ",
+            Ok(ref metadata) if metadata.is_dir() => "📁 ",
+            Ok(ref metadata) if is_executable(&entry.display_name(), metadata) => "⚙️ ",
+            _ => "📄 ",
+        }
+    }
+
+    /// 打印 `✓ ` 或 `✗ `，返回无颜色的文本
+    fn print_verify_mark(&mut self, entry: &FileItem) -> io::Result<String> {
+        let mark = self.verify_mark(entry);
+        let color = match self.verification.and_then(|v| v.status(entry)) {
+            Some(VerifyStatus::Match) => color::GREEN,
+            Some(_) => color::RED,
+            None => return Ok(mark),
+        };
+        write_color(self.term, self.config, color, &mark)?;
+        Ok(mark)
+    }
+
+    fn verify_mark(&self, entry: &FileItem) -> String {
+        let (ok, bad) = self.config.charset.verify_marks();
+        match self.verification.and_then(|v| v.status(entry)) {
+            Some(VerifyStatus::Match) => format!("{} ", ok),
+            Some(_) => format!("{} ", bad),
+            None => String::new(),
+        }
+    }
+
+    /// 清单中有但已经不存在的条目，列在树的后面
+    fn print_missing(&mut self, verification: &Verification) -> io::Result<()> {
+        let (_, bad) = self.config.charset.verify_marks();
+        let missing = self.config.lang.verify_missing();
+        for path in &verification.missing {
+            let mark = format!("{} ", bad);
+            let line = format!("{}  {}", path.display(), missing);
+            write_color(self.term, self.config, color::RED, &mark)?;
+            writeln!(self.term, "{}", line)?;
+            if let Some(ref mut buffer) = self.copy_buffer {
+                buffer.push_str(&mark);
+                buffer.push_str(&line);
+                buffer.push('\n');
+            }
+        }
+        Ok(())
+    }
+
+    /// 文件名之前的元数据列，例如 `[       4096 Jan  1 12:00]  `
+    fn line_meta(&self, node: &Node, parent_size: u64) -> String {
+        let entry = &node.item;
+        let mut fields: Vec<String> = Vec::new();
+        if self.config.bars {
+            let share = if parent_size == 0 {
+                0.0
+            } else {
+                node.size as f64 / parent_size as f64
+            };
+            fields.push(size_bar(share, self.config.charset));
+        }
+        // 跟随链接时 entry.metadata 是目标的信息；`--group-by-ext` 的虚拟条目代表多个文件，
+        // 不显示其中某一个的信息，只显示合计的大小
+        let metadata = match entry.link_metadata {
+            _ if entry.grouped.is_some() => None,
+            Some(ref link) if !self.config.target_meta => Some(link),
+            _ => entry.metadata.as_ref().ok(),
+        };
+        let ids = metadata.and_then(owner_ids);
+        if self.config.show_owner {
+            let user = ids.map(|(uid, _)| match self.id_names {
+                Some(ref names) => names.user(uid),
+                None => uid.to_string(),
+            });
+            fields.push(format!("{:<8}", user.as_deref().unwrap_or("-")));
+        }
+        if self.config.show_group {
+            let group = ids.map(|(_, gid)| match self.id_names {
+                Some(ref names) => names.group(gid),
+                None => gid.to_string(),
+            });
+            fields.push(format!("{:<8}", group.as_deref().unwrap_or("-")));
+        }
+        if self.config.show_flags {
+            let flags = metadata.and_then(file_flags);
+            fields.push(flags.map_or_else(|| "-".to_string(), flag_names));
+        }
+        if self.config.inode_usage {
+            fields.push(format!("{:>9}", node.inodes));
+        }
+        if self.config.show_size {
+            let (apparent, allocated) = match entry.grouped {
+                Some(_) => (Some(node.size), None),
+                None => (
+                    Some(metadata.map_or(0, |m| m.len())),
+                    Some(metadata.map_or(0, allocated_size)),
+                ),
+            };
+            let bytes = |size: Option<u64>| {
+                size.map_or_else(
+                    || "-".to_string(),
+                    |size| self.config.byte_format.format(size),
+                )
+            };
+            fields.push(match self.config.size_kind {
+                SizeKind::Apparent => format!("{:>11}", bytes(apparent)),
+                SizeKind::Allocated => format!("{:>11}", bytes(allocated)),
+                SizeKind::Both => format!("{:>11} {:>11}", bytes(apparent), bytes(allocated)),
+            });
+        }
+        if let Some(ref format) = self.config.time_format {
+            let time = metadata.and_then(|m| self.config.time_kind.of(m));
+            fields.push(time.map_or_else(String::new, |time| format_time(time, format)));
+        }
+        if fields.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]  ", fields.join(" "))
+        }
+    }
+
+    /// 记下目录所在的设备，按层级存放，供它下面的条目比较
+    fn track_device(&mut self, entry: &FileItem) {
+        if self.mounts.is_none() {
+            return;
+        }
+        self.dir_devices.truncate(entry.level);
+        if entry.is_dir() {
+            let metadata = entry.metadata.as_ref().ok();
+            self.dir_devices
+                .push(metadata.and_then(file_id).map(|(device, _)| device));
+        }
+    }
+
+    /// 目录与上级目录不在同一设备上，即它是挂载点时，返回 `[ext4]` 这样的标记
+    fn crossed_fs_type(&self, entry: &FileItem) -> Option<String> {
+        let mounts = self.mounts.as_ref()?;
+        let metadata = entry.metadata.as_ref().ok().filter(|m| m.is_dir())?;
+        let (device, _) = file_id(metadata)?;
+        let parent = self
+            .dir_devices
+            .get(entry.level.checked_sub(1)?)
+            .copied()
+            .flatten()?;
+        if parent == device {
+            return None;
+        }
+        Some(match mounts.fs_type(&entry.path()) {
+            Some(fs_type) => format!("[{}]", fs_type),
+            None => self.config.lang.other_filesystem().to_string(),
+        })
+    }
+
+    /// `--git-repos` 时仓库根目录的状态；git 不可用时不标出
+    fn repo_status(&self, dir: &Path) -> Option<RepoStatus> {
+        if !self.config.git_repos || !is_repo_root(dir) {
+            return None;
+        }
+        repo_status(dir).ok()
+    }
+
+    /// 文件名之后的附加标记，都用两个空格与文件名隔开，`mkdir --from` 据此去掉它们
+    fn line_suffix(&self, entry: &FileItem) -> String {
+        let lang = self.config.lang;
+        let mut suffix = String::new();
+        // 要读取目录本身的标记共用一份拼接好的路径
+        let config = self.config;
+        let reads_dir = config.git_repos || config.project_badges || config.mark_empty;
+        let dir = (entry.is_dir() && reads_dir).then(|| entry.path());
+        if let Some(count) = entry.grouped {
+            suffix.push_str(&format!("  ({})", count));
+        }
+        if let Some(repo) = dir.as_deref().and_then(|dir| self.repo_status(dir)) {
+            let (clean, dirty) = self.config.charset.verify_marks();
+            let mark = if repo.dirty { dirty } else { clean };
+            suffix.push_str(&format!("  ({} {})", repo.branch, mark));
+        }
+        if let Err(err) = &entry.metadata {
+            suffix.push_str("  ");
+            if err.kind() == io::ErrorKind::TimedOut {
+                suffix.push_str(lang.timed_out());
+            } else {
+                suffix.push_str(lang.error_file());
+            }
+        }
+        if let Some(kind) = entry.metadata.as_ref().ok().and_then(special_kind) {
+            suffix.push_str("  ");
+            suffix.push_str(lang.special_kind(kind));
+        }
+        if let (true, Ok(metadata)) = (self.config.mark_sparse, &entry.metadata) {
+            if is_zero_byte(metadata) {
+                suffix.push_str("  ");
+                suffix.push_str(lang.zero_byte());
+            } else if is_sparse(metadata) {
+                suffix.push_str("  ");
+                suffix.push_str(lang.sparse());
+            }
+        }
+        let empty = config.mark_empty && !entry.recursive;
+        if empty && dir.as_deref().is_some_and(is_empty_dir) {
+            suffix.push_str("  ");
+            suffix.push_str(lang.empty_dir());
+        }
+        if entry.target_error.is_some() {
+            suffix.push_str("  ");
+            suffix.push_str(lang.target_unreadable());
+        }
+        if let Some(err) = &entry.dir_error {
+            suffix.push_str("  ");
+            if err.kind() == io::ErrorKind::TimedOut {
+                suffix.push_str(lang.timed_out());
+            } else {
+                suffix.push_str(lang.error_opening_dir());
+            }
+        }
+        if let Some(below) = entry.below_cutoff {
+            suffix.push_str("  ");
+            suffix.push_str(&lang.below_cutoff(below.files, below.dirs));
+        }
+        if entry.recursive {
+            suffix.push_str("  ");
+            suffix.push_str(lang.recursive_not_followed());
+        }
+        if let (true, Some(dir)) = (config.project_badges, &dir) {
+            let badges = project_badges(dir);
+            if !badges.is_empty() {
+                suffix.push_str(&format!("  [{}]", badges.join(", ")));
+            }
+        }
+        if let Some(fs_type) = self.crossed_fs_type(entry) {
+            suffix.push_str("  ");
+            suffix.push_str(&fs_type);
+        }
+        match self.verification.and_then(|v| v.status(entry)) {
+            Some(VerifyStatus::Changed) => {
+                suffix.push_str("  ");
+                suffix.push_str(lang.verify_changed());
+            }
+            Some(VerifyStatus::Added) => {
+                suffix.push_str("  ");
+                suffix.push_str(lang.verify_added());
+            }
+            _ => {}
+        }
+        suffix
+    }
+}
+
+/// Print a previously built [`Tree`] with the usual branch prefixes.
+pub fn render(tree: &Tree, renderer: &mut Renderer) -> io::Result<()> {
+    if renderer.config.meta_first {
+        renderer.meta_width = meta_width(&tree.root, tree.root.size, renderer);
+    }
+    let mut symbol_switch_list: Vec<bool> = Vec::new();
+    let mut prefix = String::new();
+    let root = &tree.root;
+    render_node(
+        root,
+        root.size,
+        renderer,
+        &mut symbol_switch_list,
+        &mut prefix,
+    )?;
+    if let Some(verification) = renderer.verification {
+        renderer.print_missing(verification)?;
+    }
+    Ok(())
+}
+
+/// Print entries one at a time as they come from `items`, in listing order, for scans too
+/// large to build a [`Tree`] from. Branch lines follow [`FileItem::is_last`]; size bars,
+/// `--metafirst` alignment and `--columns` need the whole tree and are left out.
+pub fn render_stream(
+    items: impl Iterator<Item = FileItem>,
+    renderer: &mut Renderer,
+) -> io::Result<()> {
+    let mut symbol_switch_list: Vec<bool> = Vec::new();
+    let mut prefix = String::new();
+    for item in items {
+        symbol_switch_list.truncate(item.level.saturating_sub(1));
+        if item.level > 0 {
+            symbol_switch_list.push(!item.is_last);
+        }
+        set_line_prefix(
+            &symbol_switch_list,
+            &mut prefix,
+            renderer.config.charset,
+            renderer.config.indent,
+            renderer.config.max_prefix_depth,
+        );
+        let not_shown = item.not_shown.filter(|n| *n > 0);
+        renderer.print_line(&Node::new(item), 0, &prefix)?;
+        if let Some(num_entries) = not_shown {
+            symbol_switch_list.push(false);
+            set_line_prefix(
+                &symbol_switch_list,
+                &mut prefix,
+                renderer.config.charset,
+                renderer.config.indent,
+                renderer.config.max_prefix_depth,
+            );
+            renderer.print_placeholder(&prefix, num_entries)?;
+            symbol_switch_list.pop();
+        }
+    }
+    Ok(())
+}
+
+/// `--columns` 时参与多列排列的条目
+fn is_leaf(node: &Node) -> bool {
+    node.children.is_empty() && !node.item.is_dir()
+}
+
+/// 整棵树中最宽的元数据列
+fn meta_width(node: &Node, parent_size: u64, renderer: &Renderer) -> usize {
+    node.children
+        .iter()
+        .map(|child| meta_width(child, node.size, renderer))
+        .fold(
+            renderer.line_meta(node, parent_size).chars().count(),
+            usize::max,
+        )
+}
+
+fn render_node(
+    node: &Node,
+    parent_size: u64,
+    renderer: &mut Renderer,
+    symbol_switch_list: &mut Vec<bool>,
+    prefix: &mut String,
+) -> io::Result<()> {
+    set_line_prefix(
+        symbol_switch_list,
+        prefix,
+        renderer.config.charset,
+        renderer.config.indent,
+        renderer.config.max_prefix_depth,
+    );
+    renderer.print_line(node, parent_size, prefix)?;
+
+    let leaves: Vec<&Node> = match renderer.config.columns {
+        Some(_) => node
+            .children
+            .iter()
+            .filter(|child| is_leaf(child))
+            .collect(),
+        None => Vec::new(),
+    };
+    // 列所在的层级，超过 `--max-prefix-depth` 的外层合并成一个 `…`
+    let depth = symbol_switch_list.len() + 1;
+    let shown_levels = match renderer.config.max_prefix_depth {
+        Some(max_depth) if depth > max_depth => max_depth + 1,
+        _ => depth,
+    };
+    let prefix_width = renderer.config.indent * shown_levels;
+    let layout = renderer.column_layout(&leaves, node.size, prefix_width);
+    // 排成多列时先画子目录，文件作为最后一个分支放在后面
+    let branches: Vec<&Node> = match layout {
+        Some(_) => node
+            .children
+            .iter()
+            .filter(|child| !is_leaf(child))
+            .collect(),
+        None => node.children.iter().collect(),
+    };
+    let count = branches.len() + usize::from(layout.is_some());
+    for (index, child) in branches.iter().enumerate() {
+        // true 表示后面还有兄弟节点，需要继续画竖线
+        symbol_switch_list.push(index + 1 < count);
+        render_node(child, node.size, renderer, symbol_switch_list, prefix)?;
+        symbol_switch_list.pop();
+    }
+    if let Some(num_entries) = node.item.not_shown.filter(|n| *n > 0) {
+        symbol_switch_list.push(false);
+        set_line_prefix(
+            symbol_switch_list,
+            prefix,
+            renderer.config.charset,
+            renderer.config.indent,
+            renderer.config.max_prefix_depth,
+        );
+        renderer.print_placeholder(prefix, num_entries)?;
+        symbol_switch_list.pop();
+    }
+    if let Some(ref layout) = layout {
+        symbol_switch_list.push(false);
+        set_line_prefix(
+            symbol_switch_list,
+            prefix,
+            renderer.config.charset,
+            renderer.config.indent,
+            renderer.config.max_prefix_depth,
+        );
+        renderer.print_columns(&leaves, node.size, prefix, layout)?;
+        symbol_switch_list.pop();
+    }
+    Ok(())
+}
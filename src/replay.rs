@@ -0,0 +1,326 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use term::color;
+
+use crate::compare::{Scan, ScanEntry};
+use crate::core::DirSummary;
+use crate::datetime::{format_time, TimeKind};
+use crate::file_iterator::root_name;
+use crate::hidden::is_hidden;
+use crate::sort::{NameOrder, SortKey};
+use crate::symbol::{
+    encode_cp437, set_line_prefix, shorten_name, write_color, write_style, Charset,
+};
+use crate::terminal::Terminal;
+use crate::{Config, DisplayOptions};
+
+/// 保存的扫描结果中的一个条目
+struct SavedNode<'a> {
+    path: &'a Path,
+    entry: &'a ScanEntry,
+    children: Vec<SavedNode<'a>>,
+    /// 文件为自身大小，目录为其下所有列出文件的大小之和
+    size: u64,
+    /// 目录下列出的条目数
+    entries: usize,
+    /// 目录被 `-E` 排除，其下的条目沿用这个状态
+    excluded: bool,
+    /// 目录本身不应列出，其中没有条目时去掉
+    container: bool,
+}
+
+impl SavedNode<'_> {
+    fn name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or_default()
+    }
+
+    fn is_dir(&self) -> bool {
+        self.entry.kind == "directory"
+    }
+
+    fn is_link(&self) -> bool {
+        self.entry.kind == "link"
+    }
+
+    fn time(&self, kind: TimeKind) -> Option<SystemTime> {
+        time(self.entry, kind)
+    }
+}
+
+/// 保存的结果中只有修改时间，`--time` 选择其他时间时没有可用的值；导出时用了 `--timefmt`
+/// 的话修改时间也无法还原
+fn time(entry: &ScanEntry, kind: TimeKind) -> Option<SystemTime> {
+    if kind != TimeKind::Mtime {
+        return None;
+    }
+    let seconds = entry.modified.as_deref()?.parse().ok()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Render a scan saved with `--output-format json`/`ndjson` or `--sqlite` as if the saved tree
+/// were listed again, and return the counts for the report. Filters, sorting, sizes, times and
+/// colors come from `config`; options that need the entries on disk, such as owners or
+/// permissions, have nothing to show, and only modification times are saved.
+pub fn render_scan(scan: &Scan, config: &Config, t: &mut Terminal) -> io::Result<DirSummary> {
+    let mut nodes = select(scan, config);
+    let names = if config.traversal.locale_sort {
+        NameOrder::from_env()
+    } else {
+        NameOrder::Bytewise
+    };
+    for node in &mut nodes {
+        aggregate(node);
+    }
+    if !config.traversal.unsorted {
+        sort(&mut nodes, config, &names);
+    }
+
+    let display = &config.display;
+    if let Some(root) = scan.root_entry() {
+        write!(t, "{}", line_meta(root, display))?;
+    }
+    // 与遍历时一样，根目录显示为路径的最后一部分
+    let root = scan.root();
+    let name = root
+        .file_name()
+        .map_or_else(|| root_name(root), OsStr::to_owned);
+    print_name(t, display, &name.to_string_lossy(), true, false)?;
+    writeln!(t)?;
+    let mut summary = DirSummary::init();
+    let mut symbol_switch_list = Vec::new();
+    print_children(&nodes, display, t, &mut symbol_switch_list, &mut summary)?;
+    Ok(summary)
+}
+
+/// 按 `-L`、`-a`、`--hidden-only`、`-E` 和 `-P` 挑出要显示的条目，组成树。规则的先后与遍历
+/// 真实目录时相同
+fn select<'a>(scan: &'a Scan, config: &Config) -> Vec<SavedNode<'a>> {
+    let filter = &config.filter;
+    let dirs_need_files = filter.include_glob.is_some();
+    let mut roots: Vec<SavedNode> = Vec::new();
+    // 栈中第 n 个元素是当前路径上第 n + 1 层的目录
+    let mut stack: Vec<SavedNode> = Vec::new();
+    let mut skipped: Option<&Path> = None;
+    for (path, entry) in scan.entries() {
+        if skipped.is_some_and(|dir| path.starts_with(dir)) {
+            continue;
+        }
+        let depth = path.components().count();
+        while stack.len() >= depth {
+            let node = stack.pop().expect("stack is not empty");
+            attach(&mut stack, &mut roots, node, dirs_need_files);
+        }
+        let name = path.file_name().unwrap_or_default();
+        // 保存的结果中没有文件属性，只能按名字判断
+        let hidden = is_hidden(name, None);
+        let is_dir = entry.kind == "directory";
+        let inside_excluded = stack.last().is_some_and(|parent| parent.excluded);
+        let (excluded, _) = filter
+            .exclude_glob
+            .decide(name, path, is_dir, inside_excluded);
+        // 被排除的目录只有其中可能有被 `!` 重新包含的条目时才保留
+        let container = if excluded {
+            is_dir && filter.exclude_glob.may_reinclude_below(path)
+        } else {
+            filter.hidden_only && !hidden && is_dir
+        };
+        if depth > config.traversal.max_level
+            || (hidden && !filter.show_all && !filter.hidden_only)
+            || (excluded && !container)
+        {
+            // 整个目录连同其下的条目一起去掉
+            skipped = Some(path);
+            continue;
+        }
+        if !is_dir && filter.hidden_only && !hidden {
+            continue;
+        }
+        if let (false, Some(pattern)) = (is_dir, &filter.include_glob) {
+            if !pattern.is_match(name, path, is_dir) {
+                continue;
+            }
+        }
+        stack.push(SavedNode {
+            path,
+            entry,
+            children: Vec::new(),
+            size: 0,
+            entries: 0,
+            excluded,
+            container,
+        });
+    }
+    while let Some(node) = stack.pop() {
+        attach(&mut stack, &mut roots, node, dirs_need_files);
+    }
+    roots
+}
+
+/// 把读完的条目挂到上级目录下；`-P` 时没有文件的目录和没有条目的容器目录去掉
+fn attach<'a>(
+    stack: &mut [SavedNode<'a>],
+    roots: &mut Vec<SavedNode<'a>>,
+    node: SavedNode<'a>,
+    dirs_need_files: bool,
+) {
+    if node.is_dir() && (dirs_need_files || node.container) && node.children.is_empty() {
+        return;
+    }
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(node),
+        None => roots.push(node),
+    }
+}
+
+fn aggregate(node: &mut SavedNode) {
+    if !node.is_dir() {
+        node.size = node.entry.size;
+        return;
+    }
+    for child in &mut node.children {
+        aggregate(child);
+    }
+    node.size = node.children.iter().map(|child| child.size).sum();
+    node.entries = node.children.iter().map(|child| child.entries + 1).sum();
+}
+
+/// 与遍历真实目录时的顺序规则相同
+fn sort(nodes: &mut [SavedNode], config: &Config, names: &NameOrder) {
+    let keys = &config.traversal.sort_keys;
+    nodes.sort_by(|a, b| {
+        let dirs = if config.traversal.dirs_first {
+            b.is_dir().cmp(&a.is_dir())
+        } else {
+            Ordering::Equal
+        };
+        keys.iter()
+            .fold(dirs, |order, key| {
+                order.then_with(|| match key {
+                    SortKey::Name => names.compare(a.name(), b.name()),
+                    SortKey::Entries => b.entries.cmp(&a.entries),
+                    SortKey::Size => b.size.cmp(&a.size),
+                    SortKey::Mtime => {
+                        let kind = config.display.time_kind;
+                        b.time(kind).cmp(&a.time(kind))
+                    }
+                })
+            })
+            .then_with(|| names.compare(a.name(), b.name()))
+            .then_with(|| a.name().cmp(b.name()))
+    });
+    for node in nodes {
+        sort(&mut node.children, config, names);
+    }
+}
+
+fn print_children(
+    nodes: &[SavedNode],
+    display: &DisplayOptions,
+    t: &mut Terminal,
+    symbol_switch_list: &mut Vec<bool>,
+    summary: &mut DirSummary,
+) -> io::Result<()> {
+    let mut prefix = String::new();
+    for (index, node) in nodes.iter().enumerate() {
+        symbol_switch_list.push(index + 1 < nodes.len());
+        set_line_prefix(
+            symbol_switch_list,
+            &mut prefix,
+            display.charset,
+            display.indent,
+            display.max_prefix_depth,
+        );
+        if display.charset == Charset::Cp437 {
+            t.write_all(&encode_cp437(&prefix))?;
+        } else {
+            write!(t, "{}", prefix)?;
+        }
+        write!(t, "{}", line_meta(node.entry, display))?;
+        let name = node.name().to_string_lossy();
+        let name = match display.max_name_len {
+            Some(max_len) => shorten_name(&name, max_len, display.charset),
+            None => Cow::Borrowed(name.as_ref()),
+        };
+        print_name(t, display, &name, node.is_dir(), node.is_link())?;
+        if node.entry.kind == "error" {
+            write!(t, "  {}", display.lang.error_file())?;
+        }
+        writeln!(t)?;
+
+        // 保存的结果中没有占用空间和 inode，按每个条目一个 inode、占用空间等于大小计
+        summary.total_inodes += 1;
+        if node.is_link() {
+            summary.num_symlinks += 1;
+            summary.kinds.symlinks += 1;
+        } else if node.is_dir() {
+            summary.num_folders += 1;
+            summary.kinds.directories += 1;
+        } else {
+            summary.num_files += 1;
+            summary.total_size += node.entry.size;
+            summary.total_allocated += node.entry.size;
+            match node.entry.kind.as_str() {
+                "error" => summary.num_errors += 1,
+                "fifo" => summary.kinds.fifos += 1,
+                "socket" => summary.kinds.sockets += 1,
+                "char" | "block" => summary.kinds.devices += 1,
+                _ => summary.kinds.regular += 1,
+            }
+        }
+        summary.max_depth = summary.max_depth.max(symbol_switch_list.len());
+        print_children(&node.children, display, t, symbol_switch_list, summary)?;
+        symbol_switch_list.pop();
+    }
+    Ok(())
+}
+
+/// `-s` 和 `-D` 的元数据列
+fn line_meta(entry: &ScanEntry, display: &DisplayOptions) -> String {
+    let mut fields: Vec<String> = Vec::new();
+    if display.show_size {
+        fields.push(format!("{:>11}", display.byte_format.format(entry.size)));
+    }
+    if let Some(ref format) = display.time_format {
+        let shown = match time(entry, display.time_kind) {
+            Some(time) => Some(format_time(time, format)),
+            // 导出时已经格式化过的修改时间原样显示
+            None if display.time_kind == TimeKind::Mtime => entry.modified.clone(),
+            None => None,
+        };
+        fields.push(shown.unwrap_or_default());
+    }
+    if fields.is_empty() {
+        String::new()
+    } else {
+        format!("[{}]  ", fields.join(" "))
+    }
+}
+
+/// 与 [`crate::symbol::print_path`] 的颜色一致，只是类型来自保存的结果
+fn print_name(
+    t: &mut Terminal,
+    display: &DisplayOptions,
+    name: &str,
+    is_dir: bool,
+    is_link: bool,
+) -> io::Result<()> {
+    if let Some(style) = display.color_rules.style(name) {
+        return write_style(t, display, style, name);
+    }
+    if let Some(ref dircolors) = display.dircolors {
+        return match dircolors.style_by_type(name, is_dir, is_link) {
+            Some(style) => write_style(t, display, style, name),
+            None => write!(t, "{}", name),
+        };
+    }
+    if is_dir {
+        write_color(t, display, color::BRIGHT_BLUE, name)
+    } else {
+        write!(t, "{}", name)
+    }
+}
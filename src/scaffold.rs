@@ -0,0 +1,176 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::generate::Generated;
+
+/// 树形线条中竖线和缩进使用的字符，包括 GNU tree 输出的不换行空格
+const PREFIX_CHARS: [char; 4] = ['│', '|', ' ', '\u{a0}'];
+/// 分支开头的字符，后面跟着横线
+const BRANCH_CHARS: [char; 5] = ['├', '└', '|', '`', '+'];
+const LINE_CHARS: [char; 2] = ['─', '-'];
+
+/// One entry of a tree description, relative to the directory it is created in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse a tree description, either the output of tree-cli or GNU tree (whose first line is
+/// the root and maps to the target directory) or a list indented with spaces, optionally with
+/// `-`/`*` bullets and `# comments`.
+///
+/// Entries with a trailing `/` or with entries below them are directories, the rest files.
+pub fn parse(text: &str) -> Result<Vec<Entry>, ParseError> {
+    let is_tree = text.lines().any(|line| split_branch(line).is_some());
+    // 栈中是当前路径上的条目的缩进和下标
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut seen_root = false;
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: &str| ParseError {
+            line: index + 1,
+            message: message.to_string(),
+        };
+        let (indent, name) = if is_tree {
+            match split_branch(line) {
+                Some((indent, name)) => (indent, strip_decorations(name)),
+                None => {
+                    // 第一行是根目录，其余没有分支的行是报告、错误信息等
+                    seen_root |= !line.trim().is_empty();
+                    continue;
+                }
+            }
+        } else {
+            match split_item(line) {
+                Some(item) => item,
+                None => continue,
+            }
+        };
+        if is_tree && !seen_root {
+            return Err(error("entry before the root line"));
+        }
+        let (name, is_dir) = match name.strip_suffix('/') {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+        let relative = Path::new(name);
+        let valid = !name.is_empty()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !valid {
+            return Err(error(&format!("`{}` is not a relative path", name)));
+        }
+
+        while stack.last().is_some_and(|(parent, _)| *parent >= indent) {
+            stack.pop();
+        }
+        let path = match stack.last() {
+            Some((_, parent)) => {
+                entries[*parent].is_dir = true;
+                entries[*parent].path.join(relative)
+            }
+            None => relative.to_path_buf(),
+        };
+        entries.push(Entry { path, is_dir });
+        stack.push((indent, entries.len() - 1));
+    }
+    Ok(entries)
+}
+
+/// Create the entries under `root`. Existing directories and files are left as they are, so
+/// running it twice is harmless.
+pub fn create(root: &Path, entries: &[Entry]) -> io::Result<Generated> {
+    fs::create_dir_all(root)?;
+    let mut created = Generated::default();
+    for entry in entries {
+        let path = root.join(&entry.path);
+        if entry.is_dir {
+            fs::create_dir_all(&path)?;
+            created.dirs += 1;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)?;
+            created.files += 1;
+        }
+    }
+    Ok(created)
+}
+
+/// 拆出 `│   ├── name` 这样的行中名字开始的列和名字
+fn split_branch(line: &str) -> Option<(usize, &str)> {
+    let mut chars = line.char_indices().peekable();
+    let mut column = 0;
+    while let Some(&(offset, c)) = chars.peek() {
+        if BRANCH_CHARS.contains(&c) {
+            // `|` 既可以是竖线也可以是 `|--` 的开头；`--indent 2` 时 `├` 后面没有横线
+            let rest = &line[offset + c.len_utf8()..];
+            let lines = rest.chars().take_while(|c| LINE_CHARS.contains(c)).count();
+            if lines > 0 || (matches!(c, '├' | '└') && rest.starts_with(' ')) {
+                let after = &rest[rest.chars().take(lines).map(char::len_utf8).sum::<usize>()..];
+                let name = after.strip_prefix(' ')?;
+                return Some((column + 1 + lines + 1, name));
+            }
+        }
+        if !PREFIX_CHARS.contains(&c) {
+            return None;
+        }
+        chars.next();
+        column += 1;
+    }
+    None
+}
+
+/// 拆出缩进列表中一行的缩进和名字，空行和注释行返回 `None`
+fn split_item(line: &str) -> Option<(usize, &str)> {
+    let line = match line.find(" #").or_else(|| line.find("\t#")) {
+        Some(end) => &line[..end],
+        None => line,
+    };
+    let name = line.trim_start();
+    // 制表符算作四列
+    let indent: usize = line[..line.len() - name.len()]
+        .chars()
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    let name = name
+        .strip_prefix("- ")
+        .or_else(|| name.strip_prefix("* "))
+        .unwrap_or(name)
+        .trim_end();
+    (!name.is_empty() && !name.starts_with('#')).then_some((indent, name))
+}
+
+/// 去掉 tree 输出中 `-s` 等选项加在前面的 `[...]  `、符号链接的目标和 `  [...]` 这样的标记
+fn strip_decorations(name: &str) -> &str {
+    let name = name
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("]  "))
+        .map_or(name, |(_, name)| name);
+    let name = name.split(" -> ").next().unwrap_or(name);
+    let name = name.split("  ").next().unwrap_or(name);
+    name.trim_end()
+}
@@ -0,0 +1,108 @@
+use std::env;
+use std::fs::Metadata;
+
+use clap::ValueEnum;
+
+/// 二进制单位，与 GNU tree `-h` 一致按 1024 进位
+const UNITS: [&str; 6] = ["KB", "MB", "GB", "TB", "PB", "EB"];
+
+/// Format `bytes` with one decimal in the largest unit that keeps the value below 1024.
+pub fn human_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// `--bytes` 显示精确字节数的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ByteFormat {
+    /// Digits only
+    #[default]
+    Plain,
+    /// Digits grouped with the locale's thousands separator
+    Grouped,
+}
+
+impl ByteFormat {
+    pub fn format(self, bytes: u64) -> String {
+        match self {
+            ByteFormat::Plain => bytes.to_string(),
+            ByteFormat::Grouped => group_digits(bytes, locale_separator()),
+        }
+    }
+}
+
+/// 每三位插入一个分隔符
+pub fn group_digits(value: u64, separator: char) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// The thousands separator of the locale in `LC_ALL`, `LC_NUMERIC` or `LANG`: a period for
+/// most of continental Europe and Latin America, a space for French, Russian, Nordic and
+/// other languages following that convention, and a comma otherwise.
+fn locale_separator() -> char {
+    const PERIOD: &[&str] = &["de", "es", "it", "nl", "pt", "da", "id", "tr", "el", "ro"];
+    const SPACE: &[&str] = &[
+        "fr", "ru", "sv", "nb", "no", "fi", "cs", "pl", "uk", "sk", "hu",
+    ];
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|key| env::var(key).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let language = locale
+        .split(['_', '.', '@', '-'])
+        .next()
+        .unwrap_or_default();
+    if PERIOD.contains(&language) {
+        '.'
+    } else if SPACE.contains(&language) {
+        ' '
+    } else {
+        ','
+    }
+}
+
+/// 显示和统计哪一种大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SizeKind {
+    /// The file length
+    #[default]
+    Apparent,
+    /// The space allocated on disk
+    Allocated,
+    /// Both, apparent size first
+    Both,
+}
+
+/// Space the entry takes on disk, `st_blocks` × 512. Smaller than the length for sparse files
+/// and larger for small files rounded up to a whole block.
+#[cfg(unix)]
+pub fn allocated_size(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    /// `st_blocks` 的单位固定是 512 字节，与文件系统的块大小无关
+    const BLOCK_SIZE: u64 = 512;
+    metadata.blocks() * BLOCK_SIZE
+}
+
+/// 没有块数的平台上等于文件长度
+#[cfg(not(unix))]
+pub fn allocated_size(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
@@ -0,0 +1,65 @@
+use std::cmp::Ordering;
+use std::env;
+use std::ffi::OsStr;
+
+use clap::ValueEnum;
+use icu_collator::{Collator, CollatorOptions};
+use icu_locid::Locale;
+
+/// 同一目录下条目的排列依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SortKey {
+    /// Alphabetically by name
+    #[default]
+    Name,
+    /// Directories with the most entries below them first
+    Entries,
+    /// Largest first, directories by the total size of the files listed below them
+    Size,
+    /// Most recent first, by the timestamp --time picks (the modification time by default)
+    Mtime,
+}
+
+/// 同一目录下文件名的排序方式
+#[derive(Debug)]
+pub enum NameOrder {
+    /// 按字节顺序比较
+    Bytewise,
+    /// 按当前语言环境的排序规则比较
+    Locale(Box<Collator>),
+}
+
+impl NameOrder {
+    /// Build a collation-aware order for `LC_ALL`/`LC_COLLATE`/`LANG`, falling back to the
+    /// root collation when the locale is unknown.
+    pub fn from_env() -> NameOrder {
+        let locale = ["LC_ALL", "LC_COLLATE", "LANG"]
+            .iter()
+            .filter_map(|key| env::var(key).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| parse_locale(&value))
+            .unwrap_or(Locale::UND);
+        match Collator::try_new(&(&locale).into(), CollatorOptions::new()) {
+            Ok(collator) => NameOrder::Locale(Box::new(collator)),
+            Err(_) => NameOrder::Bytewise,
+        }
+    }
+
+    pub fn compare(&self, a: &OsStr, b: &OsStr) -> Ordering {
+        match self {
+            NameOrder::Bytewise => a.cmp(b),
+            NameOrder::Locale(collator) => collator
+                .compare(&a.to_string_lossy(), &b.to_string_lossy())
+                .then_with(|| a.cmp(b)),
+        }
+    }
+}
+
+/// 将 `zh_CN.UTF-8` 形式的环境变量转换为 `zh-CN`
+fn parse_locale(value: &str) -> Option<Locale> {
+    let name = value.split(['.', '@']).next()?.replace('_', "-");
+    if name == "C" || name == "POSIX" {
+        return None;
+    }
+    name.parse().ok()
+}
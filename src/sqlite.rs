@@ -0,0 +1,70 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use rusqlite::{params, Connection, Transaction};
+
+use crate::core::{Node, Traversal, Tree};
+use crate::export::entry_kind;
+use crate::file_iterator::escape_name;
+
+const SCHEMA: &str = "CREATE TABLE entries (
+    path TEXT PRIMARY KEY,
+    parent TEXT,
+    type TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    mtime INTEGER,
+    depth INTEGER NOT NULL
+)";
+
+/// Write every entry of `trees` into a new SQLite database at `file`, one row per entry in an
+/// `entries` table. An existing file is replaced. `mtime` is in seconds since the Unix epoch and
+/// `parent` is NULL for the roots. Rows are inserted in the order of `traversal`.
+pub fn write_sqlite(trees: &[Tree], file: &Path, traversal: Traversal) -> io::Result<()> {
+    match fs::remove_file(file) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    insert_trees(trees, file, traversal).map_err(io::Error::other)
+}
+
+fn insert_trees(trees: &[Tree], file: &Path, traversal: Traversal) -> rusqlite::Result<()> {
+    let mut connection = Connection::open(file)?;
+    let transaction = connection.transaction()?;
+    transaction.execute(SCHEMA, [])?;
+    for tree in trees {
+        for node in tree.nodes(traversal) {
+            insert_node(&transaction, node)?;
+        }
+    }
+    transaction.commit()
+}
+
+fn insert_node(transaction: &Transaction, node: &Node) -> rusqlite::Result<()> {
+    let item = &node.item;
+    let metadata = item.metadata.as_ref().ok();
+    let mtime = metadata
+        .and_then(|m| m.modified().ok())
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
+    let path = item.path();
+    let parent = path
+        .parent()
+        .filter(|_| item.level > 0)
+        .map(|p| escape_name(p.as_os_str()));
+    transaction
+        .prepare_cached(
+            "INSERT OR IGNORE INTO entries (path, parent, type, size, mtime, depth) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?
+        .execute(params![
+            escape_name(path.as_os_str()),
+            parent,
+            entry_kind(item),
+            metadata.map_or(0, |m| m.len() as i64),
+            mtime,
+            item.level as i64,
+        ])?;
+    Ok(())
+}
@@ -1,9 +1,16 @@
+use std::borrow::Cow;
 use std::fs::Metadata;
-use std::io;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::SystemTime;
 
+use clap::ValueEnum;
 use term::color;
 
-use crate::Config;
+use crate::datetime::TimeKind;
+use crate::terminal::Terminal;
+use crate::theme::Style;
+use crate::DisplayOptions;
 
 /// 横线
 pub const HOR: char = '─';
@@ -16,50 +23,237 @@ pub const END: char = '└';
 /// 空格
 pub const SPACE: char = ' ';
 
-pub fn set_line_prefix(symbol_switch_list: &Vec<bool>, prefix: &mut String) {
+/// 树形线条使用的字符集
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Charset {
+    /// Unicode box-drawing characters
+    #[default]
+    Utf8,
+    /// Plain ASCII (`|--`, `` `-- ``)
+    Ascii,
+    /// IBM code page 437 line graphics
+    #[value(alias = "ibm437")]
+    Cp437,
+}
+
+struct Symbols {
+    hor: char,
+    cro: char,
+    ver: char,
+    end: char,
+    /// `--max-prefix-depth` 省略的外层，以及 `--max-name-len` 截去的部分
+    elided: char,
+}
+
+impl Charset {
+    /// `--verify` 时表示一致和不一致的标记
+    pub fn verify_marks(self) -> (char, char) {
+        match self {
+            Charset::Utf8 => ('✓', '✗'),
+            Charset::Ascii | Charset::Cp437 => ('v', 'x'),
+        }
+    }
+
+    fn symbols(self) -> Symbols {
+        match self {
+            // CP437 先用 Unicode 字符拼接，输出时再由 encode_cp437 转换
+            Charset::Utf8 | Charset::Cp437 => Symbols {
+                hor: HOR,
+                cro: CRO,
+                ver: VER,
+                end: END,
+                // CP437 中没有省略号
+                elided: if self == Charset::Utf8 { '…' } else { '~' },
+            },
+            Charset::Ascii => Symbols {
+                hor: '-',
+                cro: '|',
+                ver: '|',
+                end: '`',
+                elided: '~',
+            },
+        }
+    }
+}
+
+/// 每一层缩进的默认宽度，与 GNU tree 相同
+pub const DEFAULT_INDENT: usize = 4;
+
+/// Build the branch lines in front of an entry. Each level is `indent` columns wide, at least
+/// two: the line character, then horizontal lines (for the entry's own level) or spaces. With
+/// `max_depth`, only the innermost levels are drawn and the outer ones collapse into one `…`
+/// followed by how many levels it stands for, such as `…2`, so that entries at different
+/// depths stay apart.
+pub fn set_line_prefix(
+    symbol_switch_list: &[bool],
+    prefix: &mut String,
+    charset: Charset,
+    indent: usize,
+    max_depth: Option<usize>,
+) {
+    let symbols = charset.symbols();
+    let fill = indent.saturating_sub(1);
+    prefix.clear();
+    let mut symbol_switch_list = symbol_switch_list;
+    if let Some(max_depth) = max_depth.filter(|max| symbol_switch_list.len() > *max) {
+        let elided = symbol_switch_list.len() - max_depth;
+        symbol_switch_list = &symbol_switch_list[elided..];
+        prefix.push(symbols.elided);
+        // 层数放不进这一层的宽度时只画省略符号，保持各行对齐
+        let count = elided.to_string();
+        let count = if count.len() <= fill {
+            count.as_str()
+        } else {
+            ""
+        };
+        prefix.push_str(count);
+        prefix.extend(std::iter::repeat_n(SPACE, fill - count.len()));
+    }
     let len = symbol_switch_list.len();
     let index = len.saturating_sub(1);
-    prefix.clear();
     for symbol_switch in symbol_switch_list.iter().take(index) {
         if *symbol_switch {
-            prefix.push(VER);
+            prefix.push(symbols.ver);
         } else {
             prefix.push(SPACE);
         }
-        prefix.push(SPACE);
-        prefix.push(SPACE);
-        prefix.push(SPACE);
+        prefix.extend(std::iter::repeat_n(SPACE, fill));
     }
     if let Some(symbol_switch) = symbol_switch_list.last() {
         if *symbol_switch {
-            prefix.push(CRO);
+            prefix.push(symbols.cro);
         } else {
-            prefix.push(END);
+            prefix.push(symbols.end);
         }
-        prefix.push(HOR);
-        prefix.push(HOR);
+        prefix.extend(std::iter::repeat_n(symbols.hor, fill.saturating_sub(1)));
         prefix.push(SPACE);
     }
 }
 
+/// `--max-name-len` 允许的最小值，截断后的名字要放得下省略符号、哈希和扩展名
+pub const MIN_NAME_LEN: usize = 16;
+/// 截断时保留的扩展名（含 `.`）最长的字符数，更长的不当作扩展名
+const MAX_KEPT_EXTENSION: usize = 8;
+
+/// `--bars` 的条形宽度
+const BAR_WIDTH: usize = 10;
+
+/// Shorten `name` to at most `max_len` characters (at least [`MIN_NAME_LEN`]) as its start, `…`
+/// (`~` outside UTF-8, as for elided branch lines), six hex digits hashed from the whole name
+/// and the extension, so that names sharing a long prefix, such as UUIDs, can still be told
+/// apart. Shorter names are returned unchanged.
+///
+/// ```
+/// use tree_cli::symbol::{shorten_name, Charset};
+///
+/// let name = "3f2a9c1e-77b4-4c1f-9a0e-5d8f2b6c4e1a.json";
+/// let short = shorten_name(name, 24, Charset::Utf8);
+/// assert_eq!(24, short.chars().count());
+/// assert!(short.starts_with("3f2a9c1e-77b…") && short.ends_with(".json"));
+/// assert!(shorten_name(name, 24, Charset::Ascii).is_ascii());
+/// ```
+pub fn shorten_name(name: &str, max_len: usize, charset: Charset) -> Cow<'_, str> {
+    if name.chars().count() <= max_len {
+        return Cow::Borrowed(name);
+    }
+    let extension = match name.rfind('.') {
+        Some(dot) if dot > 0 && name[dot..].chars().count() <= MAX_KEPT_EXTENSION => &name[dot..],
+        _ => "",
+    };
+    // FNV-1a，在所有平台和版本上结果相同
+    let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    let tail = format!(
+        "{}{:06x}{}",
+        charset.symbols().elided,
+        hash & 0xff_ffff,
+        extension
+    );
+    let keep = max_len.saturating_sub(tail.chars().count());
+    let mut short: String = name.chars().take(keep).collect();
+    short.push_str(&tail);
+    Cow::Owned(short)
+}
+
+/// A bar of fixed width filled in proportion to `share`, followed by the percentage.
+pub fn size_bar(share: f64, charset: Charset) -> String {
+    let share = share.clamp(0.0, 1.0);
+    let mut bar = String::with_capacity(BAR_WIDTH * 3 + 5);
+    if charset == Charset::Utf8 {
+        // 每个字符再细分为八格
+        const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+        let eighths = (share * (BAR_WIDTH * 8) as f64).round() as usize;
+        bar.extend(std::iter::repeat_n('█', eighths / 8));
+        let partial = eighths % 8;
+        if partial > 0 {
+            bar.push(PARTIAL[partial]);
+        }
+    } else {
+        let filled = (share * BAR_WIDTH as f64).round() as usize;
+        bar.extend(std::iter::repeat_n('#', filled));
+    }
+    let padding = BAR_WIDTH - bar.chars().count();
+    bar.extend(std::iter::repeat_n(' ', padding));
+    format!("{} {:>3.0}%", bar, share * 100.0)
+}
+
+/// Translate the box-drawing characters of a prefix into IBM code page 437 bytes.
+pub fn encode_cp437(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut buf = [0u8; 4];
+    for c in text.chars() {
+        match c {
+            HOR => bytes.push(0xC4),
+            CRO => bytes.push(0xC3),
+            VER => bytes.push(0xB3),
+            END => bytes.push(0xC0),
+            c => bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes()),
+        }
+    }
+    bytes
+}
+
 pub fn print_path(
     file_name: &str,
+    path: &Path,
     metadata: &Metadata,
-    t: &mut Box<term::StdoutTerminal>,
-    config: &Config,
+    t: &mut Terminal,
+    config: &DisplayOptions,
 ) -> io::Result<()> {
-    if metadata.is_dir() {
+    let rule = (!config.age_colors && !config.size_colors)
+        .then(|| config.color_rules.style(file_name))
+        .flatten();
+    if let Some(style) = rule {
+        write_style(t, config, style, file_name)
+    } else if let Some(dircolors) = &config.dircolors {
+        match dircolors.style(file_name, path, metadata) {
+            Some(style) => write_style(t, config, style, file_name),
+            None => write!(t, "{}", file_name),
+        }
+    } else if config.age_colors {
+        let color = age_color(metadata, config.time_kind, SystemTime::now());
+        write_color(t, config, color, file_name)
+    } else if metadata.is_dir() {
         write_color(t, config, color::BRIGHT_BLUE, file_name)
-    } else if is_executable(metadata) {
+    } else if let Some(kind) = special_kind(metadata) {
+        write_color(t, config, kind.color(), file_name)
+    } else if config.size_colors {
+        write_color(t, config, size_color(metadata.len()), file_name)
+    } else if config.mark_sparse && is_zero_byte(metadata) {
+        write_color(t, config, color::BRIGHT_BLACK, file_name)
+    } else if config.mark_sparse && is_sparse(metadata) {
+        write_color(t, config, color::YELLOW, file_name)
+    } else if is_executable(file_name, metadata) {
         write_color(t, config, color::BRIGHT_RED, file_name)
     } else {
         write!(t, "{}", file_name)
     }
 }
 
-fn write_color(
-    t: &mut Box<term::StdoutTerminal>,
-    config: &Config,
+pub fn write_color(
+    t: &mut Terminal,
+    config: &DisplayOptions,
     color: color::Color,
     str: &str,
 ) -> io::Result<()> {
@@ -73,35 +267,143 @@ fn write_color(
     Ok(())
 }
 
+/// 用配置文件中的样式输出
+pub fn write_style(
+    t: &mut Terminal,
+    config: &DisplayOptions,
+    style: &Style,
+    str: &str,
+) -> io::Result<()> {
+    if config.colorful {
+        t.sgr(style.sgr())?;
+    }
+    write!(t, "{}", str)?;
+    if config.colorful {
+        t.reset()?;
+    }
+    Ok(())
+}
+
+/// 按时间从近到远由亮到暗，时间未知的条目当作最旧的处理
+fn age_color(metadata: &Metadata, kind: TimeKind, now: SystemTime) -> color::Color {
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+    const GRADIENT: [(u64, color::Color); 4] = [
+        (HOUR, color::BRIGHT_WHITE),
+        (DAY, color::BRIGHT_YELLOW),
+        (7 * DAY, color::YELLOW),
+        (30 * DAY, color::WHITE),
+    ];
+    let age = kind
+        .of(metadata)
+        .map(|time| now.duration_since(time).map_or(0, |age| age.as_secs()));
+    match age {
+        Some(age) => GRADIENT
+            .iter()
+            .find(|(limit, _)| age < *limit)
+            .map_or(color::BRIGHT_BLACK, |(_, color)| *color),
+        None => color::BRIGHT_BLACK,
+    }
+}
+
+/// 文件越大颜色越醒目
+fn size_color(len: u64) -> color::Color {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+    const GRADIENT: [(u64, color::Color); 4] = [
+        (KB, color::BRIGHT_BLACK),
+        (MB, color::GREEN),
+        (100 * MB, color::YELLOW),
+        (1024 * MB, color::BRIGHT_YELLOW),
+    ];
+    GRADIENT
+        .iter()
+        .find(|(limit, _)| len < *limit)
+        .map_or(color::BRIGHT_RED, |(_, color)| *color)
+}
+
+/// 既不是普通文件、目录也不是符号链接的特殊文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialKind {
+    Fifo,
+    Socket,
+    CharDevice,
+    BlockDevice,
+}
+
+impl SpecialKind {
+    /// 与 `ls` 的默认配色相近
+    fn color(self) -> color::Color {
+        match self {
+            SpecialKind::Fifo => color::YELLOW,
+            SpecialKind::Socket => color::BRIGHT_MAGENTA,
+            SpecialKind::CharDevice | SpecialKind::BlockDevice => color::BRIGHT_YELLOW,
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn special_kind(metadata: &Metadata) -> Option<SpecialKind> {
+    use std::os::unix::fs::FileTypeExt;
+    let file_type = metadata.file_type();
+    if file_type.is_fifo() {
+        Some(SpecialKind::Fifo)
+    } else if file_type.is_socket() {
+        Some(SpecialKind::Socket)
+    } else if file_type.is_char_device() {
+        Some(SpecialKind::CharDevice)
+    } else if file_type.is_block_device() {
+        Some(SpecialKind::BlockDevice)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+pub fn special_kind(_metadata: &Metadata) -> Option<SpecialKind> {
+    None
+}
+
+/// 长度为 0 的普通文件
+pub fn is_zero_byte(metadata: &Metadata) -> bool {
+    metadata.is_file() && metadata.len() == 0
+}
+
+/// 实际分配的块远小于文件长度
+#[cfg(unix)]
+pub fn is_sparse(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.is_file()
+        && metadata.len() > metadata.blksize()
+        && crate::size::allocated_size(metadata) < metadata.len() / 2
+}
+
+#[cfg(not(unix))]
+pub fn is_sparse(_metadata: &Metadata) -> bool {
+    false
+}
+
 #[cfg(target_os = "windows")]
-fn is_executable(metadata: &Metadata) -> bool {
+pub fn is_executable(file_name: &str, metadata: &Metadata) -> bool {
     // fixme 没有windows电脑所以不确定是否正确
-    use std::os::windows::fs::{MetadataExt, PermissionsExt};
     if !metadata.is_file() || metadata.permissions().readonly() {
         return false;
     }
-    let path = match metadata.file_name().into_string() {
-        Ok(path) => path,
-        Err(_) => return false,
-    };
-    let ext = path.extension();
     let executable_extensions = ["exe", "dll", "com", "sys", "bat", "cmd"];
-    if let Some(ext_str) = ext {
-        if executable_extensions.contains(&ext_str.to_ascii_lowercase()) {
-            return true;
-        }
-    }
-    false
+    std::path::Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| executable_extensions.contains(&ext.to_ascii_lowercase().as_str()))
 }
 
 // 仅针对macOS和Windows
 #[cfg(any(target_os = "macos", target_os = "linux"))]
-fn is_executable(metadata: &Metadata) -> bool {
+pub fn is_executable(_file_name: &str, metadata: &Metadata) -> bool {
     use std::os::unix::fs::PermissionsExt;
     metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
 }
 
 #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
-fn is_executable(metadata: &Metadata) -> bool {
+pub fn is_executable(_file_name: &str, _metadata: &Metadata) -> bool {
     false
 }
@@ -0,0 +1,181 @@
+use std::io::{self, BufWriter, IsTerminal, Stdout, Write};
+
+use term::color::Color;
+
+/// 无法获取终端宽度时使用的默认值
+const DEFAULT_WIDTH: usize = 80;
+
+enum Backend {
+    /// 基于 terminfo 的终端
+    Term(Box<term::StdoutTerminal>),
+    /// 没有 terminfo 数据库时退回到带缓冲的标准输出
+    Plain(BufWriter<Stdout>),
+    /// 丢弃所有输出，用于单独测量渲染的耗时
+    Sink(io::Sink),
+    /// 保存到内存中，颜色以 ANSI 转义序列写入
+    Buffer(Vec<u8>),
+}
+
+/// 标准输出的统一封装，只检测一次 TTY 与颜色支持
+pub struct Terminal {
+    backend: Backend,
+    is_tty: bool,
+    supports_color: bool,
+}
+
+impl Terminal {
+    pub fn stdout() -> Terminal {
+        let is_tty = io::stdout().is_terminal();
+        // Windows 10 之后的控制台支持 ANSI 转义序列，直接输出比 term 的控制台 API 更可靠
+        if is_tty && enable_virtual_terminal() {
+            return Terminal {
+                backend: Backend::Plain(BufWriter::new(io::stdout())),
+                is_tty,
+                supports_color: true,
+            };
+        }
+        match term::stdout() {
+            Some(t) => {
+                let supports_color = t.supports_color();
+                Terminal {
+                    backend: Backend::Term(t),
+                    is_tty,
+                    supports_color,
+                }
+            }
+            None => Terminal {
+                backend: Backend::Plain(BufWriter::new(io::stdout())),
+                is_tty,
+                supports_color: false,
+            },
+        }
+    }
+
+    /// A terminal that discards everything written to it.
+    pub fn sink() -> Terminal {
+        Terminal {
+            backend: Backend::Sink(io::sink()),
+            is_tty: false,
+            supports_color: false,
+        }
+    }
+
+    /// A terminal that keeps everything written to it, see [`Terminal::into_buffer`].
+    pub fn buffer() -> Terminal {
+        Terminal {
+            backend: Backend::Buffer(Vec::new()),
+            is_tty: false,
+            supports_color: false,
+        }
+    }
+
+    /// The bytes written to a [`Terminal::buffer`]; empty for the other kinds.
+    pub fn into_buffer(self) -> Vec<u8> {
+        match self.backend {
+            Backend::Buffer(buffer) => buffer,
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether stdout is attached to a terminal.
+    pub fn is_tty(&self) -> bool {
+        self.is_tty
+    }
+
+    /// Width of the terminal in columns, falling back to `$COLUMNS` and then 80.
+    pub fn width(&self) -> usize {
+        if let Some((terminal_size::Width(width), _)) = terminal_size::terminal_size() {
+            return usize::from(width);
+        }
+        std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+            .unwrap_or(DEFAULT_WIDTH)
+    }
+
+    /// Whether colors should be used when the user didn't force them on or off.
+    pub fn supports_color(&self) -> bool {
+        self.is_tty && self.supports_color
+    }
+
+    /// Set the foreground color. Without terminfo the ANSI sequence is written directly.
+    pub fn fg(&mut self, color: Color) -> io::Result<()> {
+        match self.backend {
+            Backend::Term(ref mut t) => t.fg(color).map_err(io::Error::other),
+            Backend::Plain(ref mut out) => write_fg(out, color),
+            Backend::Buffer(ref mut out) => write_fg(out, color),
+            Backend::Sink(_) => Ok(()),
+        }
+    }
+
+    /// Start an SGR sequence such as `1;33`. terminfo has no way to express arbitrary
+    /// sequences, so they are written directly on every backend.
+    pub fn sgr(&mut self, codes: &str) -> io::Result<()> {
+        match self.backend {
+            Backend::Sink(_) => Ok(()),
+            _ => write!(self, "\x1b[{}m", codes),
+        }
+    }
+
+    pub fn reset(&mut self) -> io::Result<()> {
+        match self.backend {
+            Backend::Term(ref mut t) => t.reset().map_err(io::Error::other),
+            Backend::Plain(ref mut out) => write!(out, "\x1b[0m"),
+            Backend::Buffer(ref mut out) => write!(out, "\x1b[0m"),
+            Backend::Sink(_) => Ok(()),
+        }
+    }
+}
+
+impl Write for Terminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.backend {
+            Backend::Term(ref mut t) => t.write(buf),
+            Backend::Plain(ref mut out) => out.write(buf),
+            Backend::Sink(ref mut out) => out.write(buf),
+            Backend::Buffer(ref mut out) => out.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.backend {
+            Backend::Term(ref mut t) => t.flush(),
+            Backend::Plain(ref mut out) => out.flush(),
+            Backend::Sink(ref mut out) => out.flush(),
+            Backend::Buffer(ref mut out) => out.flush(),
+        }
+    }
+}
+
+/// 没有 terminfo 时直接写出前景色的转义序列
+fn write_fg(out: &mut impl Write, color: Color) -> io::Result<()> {
+    let code = if color < 8 {
+        30 + color
+    } else {
+        90 + color - 8
+    };
+    write!(out, "\x1b[{}m", code)
+}
+
+/// 为 Windows 控制台开启 ENABLE_VIRTUAL_TERMINAL_PROCESSING
+#[cfg(windows)]
+fn enable_virtual_terminal() -> bool {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, CONSOLE_MODE,
+        ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_OUTPUT_HANDLE,
+    };
+    unsafe {
+        let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+        let mut mode: CONSOLE_MODE = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return false;
+        }
+        mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+            || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+#[cfg(not(windows))]
+fn enable_virtual_terminal() -> bool {
+    false
+}
@@ -0,0 +1,121 @@
+//! Helpers for tests that list real directories, enabled with the `test_utils` feature.
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use crate::core::build_tree;
+use crate::render::{render, Renderer};
+use crate::symbol::DEFAULT_INDENT;
+use crate::terminal::Terminal;
+use crate::Config;
+
+/// A directory tree under the system temp directory, removed again when dropped.
+///
+/// Each line of the spec creates one entry, along with any missing parent directories:
+///
+/// - `dir/` creates an empty directory
+/// - `link -> target` creates a symlink (on Windows a file symlink)
+/// - `name = contents` creates a file with the given contents
+/// - anything else creates a file whose contents are its own path
+///
+/// ```
+/// use tree_cli::test_utils::Fixture;
+///
+/// let root = Fixture::new("doc-example", &["src/main.rs", "docs/", "README -> docs"]);
+/// assert!(root.join("src/main.rs").is_file());
+/// ```
+#[derive(Debug)]
+pub struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    /// Create the tree in a fresh directory named after `name` and the process id, replacing
+    /// whatever an earlier run left there.
+    ///
+    /// Panics when an entry cannot be created, like an assertion in the test using it.
+    pub fn new(name: &str, spec: &[&str]) -> Fixture {
+        let root = std::env::temp_dir().join(format!("tree-cli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let fixture = Fixture { root };
+        for entry in spec {
+            fixture.add(entry);
+        }
+        fixture
+    }
+
+    /// Add one more entry written like a line of the spec.
+    pub fn add(&self, entry: &str) -> &Fixture {
+        if let Some((link, target)) = entry.split_once(" -> ") {
+            let path = self.prepare(link);
+            symlink(Path::new(target), &path).unwrap();
+        } else if let Some((file, contents)) = entry.split_once(" = ") {
+            fs::write(self.prepare(file), contents).unwrap();
+        } else if entry.ends_with('/') {
+            fs::create_dir_all(self.root.join(entry)).unwrap();
+        } else {
+            fs::write(self.prepare(entry), entry).unwrap();
+        }
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// 创建上级目录，返回条目的完整路径
+    fn prepare(&self, entry: &str) -> PathBuf {
+        let path = self.root.join(entry);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        path
+    }
+}
+
+impl Deref for Fixture {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl AsRef<Path> for Fixture {
+    fn as_ref(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+/// The options the command line uses when no flags are given, colors off.
+pub fn default_config() -> Config {
+    let mut config = Config::default();
+    config.display.indent = DEFAULT_INDENT;
+    config
+}
+
+/// List `path` with `config` and return the rendered tree, without the report line. Colors, if
+/// `config.display.colorful` is set, are kept as ANSI escape sequences.
+pub fn render_to_string(path: &Path, config: &Config) -> String {
+    let tree = build_tree(path, config);
+    let mut buffer = Terminal::buffer();
+    let mut renderer = Renderer::new(&config.display, &mut buffer);
+    render(&tree, &mut renderer).expect("writing to memory does not fail");
+    String::from_utf8_lossy(&buffer.into_buffer()).into_owned()
+}
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, Metadata};
+use std::path::Path;
+
+use globset::{Glob, GlobMatcher};
+
+use crate::symbol::{special_kind, SpecialKind};
+
+/// 颜色名称，下标就是 ANSI 颜色编号
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// How a name is displayed, kept as the parameters of an ANSI SGR sequence such as `1;33`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Style {
+    sgr: String,
+}
+
+impl Style {
+    /// Parse a style written as words, e.g. `cyan`, `bold yellow`, `bright red` or
+    /// `underline white on blue`.
+    pub fn parse(spec: &str) -> Result<Style, StyleError> {
+        let mut codes: Vec<String> = Vec::new();
+        let mut bright = false;
+        let mut background = false;
+        for word in spec.split_whitespace() {
+            let word = word.to_ascii_lowercase();
+            let attribute = match word.as_str() {
+                "bold" => Some(1),
+                "dim" => Some(2),
+                "italic" => Some(3),
+                "underline" => Some(4),
+                "blink" => Some(5),
+                "reverse" => Some(7),
+                _ => None,
+            };
+            if let Some(code) = attribute {
+                codes.push(code.to_string());
+                continue;
+            }
+            match word.as_str() {
+                "bright" => bright = true,
+                "on" => background = true,
+                _ => {
+                    let name = match word.strip_prefix("bright_") {
+                        Some(name) => {
+                            bright = true;
+                            name
+                        }
+                        None => word.as_str(),
+                    };
+                    let index = COLOR_NAMES
+                        .iter()
+                        .position(|color| *color == name)
+                        .ok_or_else(|| StyleError(spec.to_string()))?;
+                    let base = match (background, bright) {
+                        (false, false) => 30,
+                        (false, true) => 90,
+                        (true, false) => 40,
+                        (true, true) => 100,
+                    };
+                    codes.push((base + index).to_string());
+                    bright = false;
+                    background = false;
+                }
+            }
+        }
+        // 以 bright 或 on 结尾、或者什么都没写都是写错了
+        if codes.is_empty() || bright || background {
+            return Err(StyleError(spec.to_string()));
+        }
+        Ok(Style {
+            sgr: codes.join(";"),
+        })
+    }
+
+    /// Use SGR parameters as written in a `dircolors` database or `LS_COLORS`, e.g. `01;34`.
+    pub fn from_sgr(codes: &str) -> Result<Style, StyleError> {
+        let valid = !codes.is_empty()
+            && codes
+                .split(';')
+                .all(|code| !code.is_empty() && code.bytes().all(|b| b.is_ascii_digit()));
+        if !valid {
+            return Err(StyleError(codes.to_string()));
+        }
+        Ok(Style {
+            sgr: codes.to_string(),
+        })
+    }
+
+    /// SGR 参数，写在 `ESC [` 和 `m` 之间
+    pub fn sgr(&self) -> &str {
+        &self.sgr
+    }
+}
+
+#[derive(Debug)]
+pub struct StyleError(String);
+
+impl fmt::Display for StyleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid style '{}' (expected words like `bold`, `bright red` or `white on blue`)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for StyleError {}
+
+/// Color rules matched against entry names, taking precedence over the built-in colors. When
+/// several rules match, the last one wins.
+#[derive(Debug, Clone, Default)]
+pub struct ColorRules {
+    rules: Vec<(GlobMatcher, Style)>,
+}
+
+impl ColorRules {
+    /// 按配置文件中的顺序添加一条规则
+    pub fn push(&mut self, pattern: &str, style: &str) -> Result<(), String> {
+        let glob = Glob::new(pattern).map_err(|err| err.to_string())?;
+        let style = Style::parse(style).map_err(|err| err.to_string())?;
+        self.rules.push((glob.compile_matcher(), style));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn style(&self, file_name: &str) -> Option<&Style> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(glob, _)| glob.is_match(file_name))
+            .map(|(_, style)| style)
+    }
+}
+
+/// `dircolors` 数据库中的文件类型关键字，同一类型有多个别名
+const DIRCOLORS_KEYWORDS: [(&str, &str); 38] = [
+    ("NORMAL", "no"),
+    ("NORM", "no"),
+    ("FILE", "fi"),
+    ("RESET", "rs"),
+    ("DIR", "di"),
+    ("LNK", "ln"),
+    ("LINK", "ln"),
+    ("SYMLINK", "ln"),
+    ("ORPHAN", "or"),
+    ("MISSING", "mi"),
+    ("FIFO", "pi"),
+    ("PIPE", "pi"),
+    ("SOCK", "so"),
+    ("BLK", "bd"),
+    ("BLOCK", "bd"),
+    ("CHR", "cd"),
+    ("CHAR", "cd"),
+    ("DOOR", "do"),
+    ("EXEC", "ex"),
+    ("LEFT", "lc"),
+    ("LEFTCODE", "lc"),
+    ("RIGHT", "rc"),
+    ("RIGHTCODE", "rc"),
+    ("END", "ec"),
+    ("ENDCODE", "ec"),
+    ("SUID", "su"),
+    ("SETUID", "su"),
+    ("SGID", "sg"),
+    ("SETGID", "sg"),
+    ("STICKY", "st"),
+    ("OTHER_WRITABLE", "ow"),
+    ("OWR", "ow"),
+    ("STICKY_OTHER_WRITABLE", "tw"),
+    ("OWT", "tw"),
+    ("CAPABILITY", "ca"),
+    ("MULTIHARDLINK", "mh"),
+    ("CLRTOEOL", "cl"),
+    ("HARDLINK", "mh"),
+];
+
+/// Colors read from a `dircolors` database (the `DIR_COLORS` format), chosen by file type the
+/// way `ls` chooses them.
+#[derive(Debug, Clone, Default)]
+pub struct DirColors {
+    /// 按 `LS_COLORS` 中的两个字母的缩写保存
+    kinds: HashMap<&'static str, Style>,
+    /// 后缀和样式，后面的优先
+    suffixes: Vec<(String, Style)>,
+    /// `LINK target`：符号链接使用目标的颜色
+    link_as_target: bool,
+}
+
+impl DirColors {
+    /// Parse a database as printed by `dircolors --print-database`. `TERM` and `COLORTERM`
+    /// sections are not checked: every entry applies.
+    pub fn parse(text: &str) -> Result<DirColors, DirColorsError> {
+        let mut colors = DirColors::default();
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| DirColorsError {
+                line: index + 1,
+                message,
+            };
+            let line = match line.find(" #").or_else(|| line.find("\t#")) {
+                Some(end) => &line[..end],
+                None => line,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, value) = line
+                .split_once(char::is_whitespace)
+                .map(|(keyword, value)| (keyword, value.trim()))
+                .ok_or_else(|| error(format!("missing value for `{}`", line)))?;
+            match keyword.to_ascii_uppercase().as_str() {
+                "TERM" | "COLORTERM" | "COLOR" | "OPTIONS" | "EIGHTBIT" => continue,
+                _ => {}
+            }
+            if let Some(suffix) = keyword
+                .strip_prefix('*')
+                .or_else(|| keyword.starts_with('.').then_some(keyword))
+            {
+                let style = Style::from_sgr(value).map_err(|err| error(err.to_string()))?;
+                colors.suffixes.push((suffix.to_lowercase(), style));
+                continue;
+            }
+            let kind = DIRCOLORS_KEYWORDS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(keyword))
+                .map(|(_, kind)| *kind)
+                .ok_or_else(|| error(format!("unrecognized keyword `{}`", keyword)))?;
+            if kind == "ln" && value.eq_ignore_ascii_case("target") {
+                colors.link_as_target = true;
+                continue;
+            }
+            let style = Style::from_sgr(value).map_err(|err| error(err.to_string()))?;
+            colors.link_as_target &= kind != "ln";
+            colors.kinds.insert(kind, style);
+        }
+        Ok(colors)
+    }
+
+    /// Pick the style for an entry from the metadata of the entry itself (not of a symlink's
+    /// target). `None` means the name is printed uncolored.
+    pub fn style(&self, file_name: &str, path: &Path, metadata: &Metadata) -> Option<&Style> {
+        let file_type = metadata.file_type();
+        if file_type.is_symlink() {
+            return match fs::metadata(path) {
+                Err(_) => self.kind("or").or_else(|| self.kind("ln")),
+                Ok(target) if self.link_as_target => self.style(file_name, path, &target),
+                Ok(_) => self.kind("ln"),
+            };
+        }
+        let mode = unix_mode(metadata);
+        if file_type.is_dir() {
+            let kind = match (mode & 0o1000 != 0, mode & 0o002 != 0) {
+                (true, true) => self.kind("tw"),
+                (false, true) => self.kind("ow"),
+                (true, false) => self.kind("st"),
+                (false, false) => None,
+            };
+            return kind.or_else(|| self.kind("di"));
+        }
+        if !file_type.is_file() {
+            return match special_kind(metadata)? {
+                SpecialKind::Fifo => self.kind("pi"),
+                SpecialKind::Socket => self.kind("so"),
+                SpecialKind::CharDevice => self.kind("cd"),
+                SpecialKind::BlockDevice => self.kind("bd"),
+            };
+        }
+        let kind = [
+            (mode & 0o4000 != 0, "su"),
+            (mode & 0o2000 != 0, "sg"),
+            (mode & 0o111 != 0, "ex"),
+            (hard_links(metadata) > 1, "mh"),
+        ]
+        .into_iter()
+        .filter(|(applies, _)| *applies)
+        .find_map(|(_, kind)| self.kind(kind));
+        kind.or_else(|| self.suffix(file_name))
+            .or_else(|| self.kind("fi"))
+    }
+
+    /// Pick the style for an entry known only by its name and type, as in a saved scan.
+    pub fn style_by_type(&self, file_name: &str, is_dir: bool, is_link: bool) -> Option<&Style> {
+        if is_link {
+            self.kind("ln")
+        } else if is_dir {
+            self.kind("di")
+        } else {
+            self.suffix(file_name).or_else(|| self.kind("fi"))
+        }
+    }
+
+    fn kind(&self, kind: &str) -> Option<&Style> {
+        self.kinds.get(kind)
+    }
+
+    /// 后缀不区分大小写，和新版本的 `ls` 一样
+    fn suffix(&self, file_name: &str) -> Option<&Style> {
+        let file_name = file_name.to_lowercase();
+        self.suffixes
+            .iter()
+            .rev()
+            .find(|(suffix, _)| file_name.ends_with(suffix.as_str()))
+            .map(|(_, style)| style)
+    }
+}
+
+#[derive(Debug)]
+pub struct DirColorsError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for DirColorsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for DirColorsError {}
+
+#[cfg(unix)]
+fn unix_mode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode()
+}
+
+#[cfg(not(unix))]
+fn unix_mode(_metadata: &Metadata) -> u32 {
+    0
+}
+
+#[cfg(unix)]
+fn hard_links(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+fn hard_links(_metadata: &Metadata) -> u64 {
+    1
+}
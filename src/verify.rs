@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use crate::core::{Node, Tree};
+use crate::export::{entry_kind, read_rows};
+use crate::file_iterator::{escape_name, FileItem, FileIterator};
+use crate::hidden::is_hidden;
+use crate::Config;
+
+/// 清单中记录的条目类型和大小
+struct Expected {
+    kind: String,
+    size: u64,
+}
+
+/// 之前用 `--output-format ndjson` 导出的清单，路径相对于清单的根目录
+pub struct Manifest {
+    entries: HashMap<PathBuf, Expected>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> io::Result<Manifest> {
+        Manifest::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Manifest> {
+        let rows = read_rows(text)?;
+        let root = rows
+            .iter()
+            .find(|row| row.depth == 0)
+            .map(|row| PathBuf::from(&row.path))
+            .unwrap_or_default();
+        let entries = rows
+            .into_iter()
+            .filter(|row| row.depth > 0)
+            .map(|row| {
+                let path = Path::new(&row.path);
+                let relative = path.strip_prefix(&root).unwrap_or(path).to_path_buf();
+                let expected = Expected {
+                    kind: row.kind,
+                    size: row.size,
+                };
+                (relative, expected)
+            })
+            .collect();
+        Ok(Manifest { entries })
+    }
+}
+
+/// 单个条目和清单比较的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyStatus {
+    Match,
+    /// 类型或文件大小与清单不同
+    Changed,
+    /// 清单中没有这个条目
+    Added,
+}
+
+/// 按所在目录和文件名记录的比较结果，查找时不用拼接条目的路径
+type Statuses = HashMap<PathBuf, HashMap<OsString, VerifyStatus>>;
+
+pub struct Verification {
+    statuses: Statuses,
+    /// 清单中有但磁盘上没有的条目，相对路径
+    pub missing: Vec<PathBuf>,
+}
+
+impl Verification {
+    pub fn status(&self, item: &FileItem) -> Option<VerifyStatus> {
+        let dir = self.statuses.get(item.dir()?)?;
+        dir.get(&*item.file_name).copied()
+    }
+
+    /// 与清单不一致的条目数，包括缺失的条目
+    pub fn num_mismatches(&self) -> usize {
+        let differing = self
+            .statuses
+            .values()
+            .flat_map(HashMap::values)
+            .filter(|status| **status != VerifyStatus::Match)
+            .count();
+        differing + self.missing.len()
+    }
+}
+
+/// Compare every listed entry against `manifest` by its path relative to the root. Files must
+/// keep their type and size; directories and links only their type. An entry of the manifest
+/// counts as missing only if the directory it was in was listed and read, and the filters in
+/// `config` would have listed it; entries below `-L` or hidden by `-a`, `-P` or `-E` are not
+/// checked.
+pub fn verify(tree: &Tree, manifest: &Manifest, config: &Config) -> Verification {
+    let root = tree.root.item.path();
+    let mut statuses = HashMap::new();
+    let mut seen = HashSet::new();
+    // 读取了内容的目录，相对路径，以及是否被 `-E` 排除
+    let mut read_dirs = HashMap::new();
+    if is_read(&tree.root.item, config) {
+        read_dirs.insert(PathBuf::new(), tree.root.item.excluded);
+    }
+    let mut context = Context {
+        root: &root,
+        config,
+        manifest,
+        statuses: &mut statuses,
+        seen: &mut seen,
+        read_dirs: &mut read_dirs,
+    };
+    for child in &tree.root.children {
+        compare_node(child, &mut context);
+    }
+    let filter = FileIterator::new(&root, &config.traversal, &config.filter);
+    let mut missing: Vec<PathBuf> = manifest
+        .entries
+        .iter()
+        .filter(|(path, _)| !seen.contains(*path))
+        .filter(|(path, expected)| {
+            let parent = path.parent().unwrap_or(Path::new(""));
+            let Some(&inside_excluded) = read_dirs.get(parent) else {
+                return false;
+            };
+            let name = path.file_name().unwrap_or_default();
+            let is_dir = expected.kind == "directory";
+            let verdict = filter.verdict_for(
+                &root.join(path),
+                is_dir,
+                is_hidden(name, None),
+                inside_excluded,
+            );
+            verdict.listed || verdict.container
+        })
+        .map(|(path, _)| path.clone())
+        .collect();
+    missing.sort();
+    Verification { statuses, missing }
+}
+
+struct Context<'a> {
+    root: &'a Path,
+    config: &'a Config,
+    manifest: &'a Manifest,
+    statuses: &'a mut Statuses,
+    seen: &'a mut HashSet<PathBuf>,
+    read_dirs: &'a mut HashMap<PathBuf, bool>,
+}
+
+/// 清单中的路径是导出时转义过的文本，磁盘上的路径按同样的方式转义后才能比较
+fn manifest_key(relative: &Path) -> PathBuf {
+    PathBuf::from(escape_name(relative.as_os_str()).as_ref())
+}
+
+/// 目录的内容是否读取过：在 `-L` 之内、读取没有出错，也不是符号链接形成的环
+fn is_read(item: &FileItem, config: &Config) -> bool {
+    item.is_dir()
+        && item.level < config.traversal.max_level
+        && item.dir_error.is_none()
+        && !item.recursive
+}
+
+fn compare_node(node: &Node, context: &mut Context) {
+    let item = &node.item;
+    let path = item.path();
+    let relative = manifest_key(path.strip_prefix(context.root).unwrap_or(&path));
+    let status = match context.manifest.entries.get(&relative) {
+        None => VerifyStatus::Added,
+        Some(expected) => {
+            let kind = entry_kind(item);
+            let size = item.metadata.as_ref().map_or(0, |m| m.len());
+            if expected.kind != kind || (kind == "file" && expected.size != size) {
+                VerifyStatus::Changed
+            } else {
+                VerifyStatus::Match
+            }
+        }
+    };
+    if is_read(item, context.config) {
+        context.read_dirs.insert(relative.clone(), item.excluded);
+    }
+    context.seen.insert(relative);
+    let dir = item.dir().unwrap_or(Path::new(""));
+    context
+        .statuses
+        .entry(dir.to_path_buf())
+        .or_default()
+        .insert(item.file_name.clone().into(), status);
+    for child in &node.children {
+        compare_node(child, context);
+    }
+}
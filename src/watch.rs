@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local, SecondsFormat};
+use clap::ValueEnum;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use serde::Serialize;
+
+use crate::file_iterator::escape_name;
+
+/// 收到第一个事件后再等这么久，把同一次操作产生的多个事件合成一批
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// `--events` 的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EventFormat {
+    /// One JSON object per line with `path`, `kind` and `timestamp`
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Create,
+    Modify,
+    Delete,
+}
+
+/// A single change under a watched root.
+#[derive(Debug, Clone)]
+pub struct ChangeEvent {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+    pub time: SystemTime,
+}
+
+#[derive(Serialize)]
+struct JsonEvent<'a> {
+    path: &'a str,
+    kind: ChangeKind,
+    timestamp: String,
+}
+
+impl ChangeEvent {
+    /// 一行 JSON，时间戳是带时区的 RFC 3339
+    pub fn to_json(&self) -> String {
+        let event = JsonEvent {
+            path: &escape_name(self.path.as_os_str()),
+            kind: self.kind,
+            timestamp: DateTime::<Local>::from(self.time)
+                .to_rfc3339_opts(SecondsFormat::Millis, false),
+        };
+        serde_json::to_string(&event).unwrap_or_default()
+    }
+}
+
+/// Recursively watches the roots and hands out changes in batches.
+pub struct Watcher {
+    // 丢弃后就不再收到事件
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl Watcher {
+    pub fn new(roots: &[PathBuf]) -> notify::Result<Watcher> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        for root in roots {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+        }
+        Ok(Watcher {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Block until the next change and return it as soon as it arrives. Returns `None` once
+    /// the watcher stops, and an error when it reports one.
+    pub fn next(&self) -> Option<notify::Result<Vec<ChangeEvent>>> {
+        let event = self.events.recv().ok()?;
+        Some(event.map(|event| change_events(&event)))
+    }
+
+    /// Like [`Watcher::next`], but also collect the changes that follow within a short window,
+    /// so one operation touching many files leads to one batch.
+    pub fn wait(&self) -> Option<notify::Result<Vec<ChangeEvent>>> {
+        let mut changes = Vec::new();
+        let mut pending = Some(self.events.recv().ok()?);
+        while let Some(event) = pending.take() {
+            match event {
+                Ok(event) => changes.extend(change_events(&event)),
+                Err(err) => return Some(Err(err)),
+            }
+            pending = self.events.recv_timeout(DEBOUNCE).ok();
+        }
+        Some(Ok(changes))
+    }
+}
+
+/// 把 notify 的事件归为创建、修改和删除；重命名看作删除旧路径并创建新路径，只读访问忽略。
+/// 不知道方向的重命名按路径现在是否存在判断
+fn change_events(event: &Event) -> Vec<ChangeEvent> {
+    let time = SystemTime::now();
+    let change = |path: &Path, kind| ChangeEvent {
+        path: path.to_path_buf(),
+        kind,
+        time,
+    };
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => event
+            .paths
+            .iter()
+            .map(|path| change(path, ChangeKind::Create))
+            .collect(),
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => event
+            .paths
+            .iter()
+            .map(|path| change(path, ChangeKind::Delete))
+            .collect(),
+        // inotify 在 From 和 To 之外还会再报告一次 Both，只用前两个
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => Vec::new(),
+        EventKind::Modify(ModifyKind::Name(_)) => event
+            .paths
+            .iter()
+            .map(|path| {
+                let kind = if path.symlink_metadata().is_ok() {
+                    ChangeKind::Create
+                } else {
+                    ChangeKind::Delete
+                };
+                change(path, kind)
+            })
+            .collect(),
+        EventKind::Modify(_) => event
+            .paths
+            .iter()
+            .map(|path| change(path, ChangeKind::Modify))
+            .collect(),
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => Vec::new(),
+    }
+}
@@ -1,5 +1,10 @@
 #[cfg(test)]
 mod test {
+    use std::fs;
+    use std::process::{Command, Output};
+
+    use tree_cli::test_utils::{default_config, render_to_string, Fixture};
+
     #[test]
     fn hello() {
         let hello = "Hello, world!";
@@ -7,4 +12,929 @@ mod test {
 
         assert_eq!("Hello, world!", hello)
     }
+
+    fn tree_cli(args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_tree-cli"))
+            .args(["-N", "--lang", "en"])
+            .args(args)
+            .output()
+            .unwrap()
+    }
+
+    fn stdout(output: &Output) -> String {
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    #[test]
+    fn level_one_lists_immediate_children() {
+        let root = Fixture::new("level-one", &["a/b/c.txt", "d.txt"]);
+        let output = tree_cli(&["-L", "1", root.to_str().unwrap()]);
+        let text = stdout(&output);
+
+        assert!(output.status.success());
+        assert!(text.contains("├── a\n"));
+        assert!(text.contains("└── d.txt\n"));
+        assert!(!text.contains("── b"));
+        assert!(text.ends_with("\n1 directories, 1 files\n"));
+    }
+
+    #[test]
+    fn library_renders_without_the_report() {
+        let root = Fixture::new("library", &["a/b.txt = hello", "c/"]);
+        let mut config = default_config();
+        config.traversal.dirs_first = true;
+        let text = render_to_string(&root, &config);
+
+        assert!(text.ends_with("\n├── a\n│   └── b.txt\n└── c\n"));
+        assert_eq!("hello", fs::read_to_string(root.join("a/b.txt")).unwrap());
+    }
+
+    #[test]
+    fn level_zero_is_an_error() {
+        let root = Fixture::new("level-zero", &["a.txt"]);
+        let output = tree_cli(&["-L", "0", root.to_str().unwrap()]);
+
+        assert_eq!(Some(1), output.status.code());
+        assert!(output.stdout.is_empty());
+
+        // clap 拒绝的参数也是用法错误
+        for args in [&["-L", "abc"][..], &["--no-such-flag"]] {
+            let output = tree_cli(&[args, &[root.to_str().unwrap()]].concat());
+            assert_eq!(Some(1), output.status.code());
+        }
+        assert_eq!(Some(0), tree_cli(&["--help"]).status.code());
+    }
+
+    #[test]
+    fn indent_sets_the_width_of_each_level() {
+        let root = Fixture::new("indent", &["a/b/c.txt"]);
+        let text = stdout(&tree_cli(&["--indent", "3", root.to_str().unwrap()]));
+        assert!(text.contains("\n└─ a\n   └─ b\n      └─ c.txt\n"));
+
+        let text = stdout(&tree_cli(&["--compact", root.to_str().unwrap()]));
+        assert!(text.contains("\n└ a\n  └ b\n    └ c.txt\n"));
+    }
+
+    #[test]
+    fn sort_keys_break_ties_in_order() {
+        // 测试文件的内容就是它的路径，所以 a.txt 和 b.txt 一样大
+        let root = Fixture::new("sort-keys", &["b.txt", "a.txt", "long.txt"]);
+        let text = stdout(&tree_cli(&["--sort", "size,name", root.to_str().unwrap()]));
+        assert!(text.contains("\n├── long.txt\n├── a.txt\n└── b.txt\n"));
+    }
+
+    #[test]
+    fn root_is_not_counted() {
+        let root = Fixture::new("root-count", &["a/b.txt", "c/"]);
+        let output = tree_cli(&[root.to_str().unwrap()]);
+        assert!(stdout(&output).ends_with("\n2 directories, 1 files\n"));
+
+        let file = root.join("a/b.txt");
+        let output = tree_cli(&[file.to_str().unwrap()]);
+        assert!(stdout(&output).ends_with("\n0 directories, 0 files\n"));
+    }
+
+    #[test]
+    fn missing_root_exits_with_two() {
+        let output = tree_cli(&["/this/path/does/not/exist"]);
+
+        assert_eq!(Some(2), output.status.code());
+        assert!(!output.stderr.is_empty());
+    }
+
+    #[test]
+    fn assert_thresholds_fail_with_three() {
+        let root = Fixture::new("assert", &["a/b.txt", "c.txt", "d.txt"]);
+        let output = tree_cli(&["--assert-max-files", "2", root.to_str().unwrap()]);
+
+        assert_eq!(Some(3), output.status.code());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("check=max-files limit=2 actual=3"));
+
+        let output = tree_cli(&["--assert-max-files", "3", root.to_str().unwrap()]);
+        assert!(output.status.success());
+    }
+
+    #[test]
+    fn unknown_output_format_lists_the_formats() {
+        let output = tree_cli(&["--output-format", "xml", "."]);
+        assert_eq!(Some(1), output.status.code());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("unknown format 'xml'"));
+        assert!(stderr.contains("\n  ndjson@1  one object per entry"));
+    }
+
+    /// 在 `dir` 下创建名字不是有效 UTF-8 的文件 `bad\xFFname`
+    #[cfg(target_os = "linux")]
+    fn add_non_utf8_file(dir: &std::path::Path) {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        fs::write(dir.join(OsStr::from_bytes(b"bad\xFFname")), "").unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn exports_escape_names_that_are_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = Fixture::new("export-non-utf8", &["t/"]);
+        add_non_utf8_file(&root.join("t"));
+        let output = tree_cli(&["--output-format", "ndjson", root.to_str().unwrap()]);
+        let text = stdout(&output);
+
+        let row = text.lines().find(|line| line.contains("bad")).unwrap();
+        assert!(row.contains(r#"/t/bad\\xFFname","name":"bad\\xFFname""#));
+        assert!(!text.contains('\u{FFFD}'));
+
+        // 路径也出现在 `--errors json` 的输出中
+        let link = root.join(OsStr::from_bytes(b"dangling\xFF"));
+        std::os::unix::fs::symlink("nowhere", link).unwrap();
+        let output = tree_cli(&["-l", "--errors", "json", root.to_str().unwrap()]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(r#"/dangling\\xFF","operation":"link_target""#));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn verify_matches_names_that_are_not_utf8() {
+        let root = Fixture::new("verify-non-utf8", &["t/a.txt"]);
+        add_non_utf8_file(&root.join("t"));
+        let manifest = root.with_extension("ndjson");
+        let output = tree_cli(&["--output-format", "ndjson", root.to_str().unwrap()]);
+        fs::write(&manifest, &output.stdout).unwrap();
+
+        let output = tree_cli(&[
+            "--verify",
+            manifest.to_str().unwrap(),
+            root.to_str().unwrap(),
+        ]);
+        assert!(output.status.success(), "{}", stdout(&output));
+    }
+
+    #[test]
+    fn verify_marks_entries_against_manifest() {
+        let root = Fixture::new("verify", &["a/b.txt", "c.txt"]);
+        let manifest = root.with_extension("ndjson");
+        let output = tree_cli(&["--output-format", "ndjson", root.to_str().unwrap()]);
+        fs::write(&manifest, &output.stdout).unwrap();
+
+        let args = [
+            "--verify",
+            manifest.to_str().unwrap(),
+            root.to_str().unwrap(),
+        ];
+        let output = tree_cli(&args);
+        assert!(output.status.success());
+        assert!(stdout(&output).contains("└── ✓ c.txt\n"));
+
+        fs::write(root.join("c.txt"), "changed").unwrap();
+        fs::remove_file(root.join("a/b.txt")).unwrap();
+        let output = tree_cli(&args);
+        let text = stdout(&output);
+        assert_eq!(Some(3), output.status.code());
+        assert!(text.contains("└── ✗ c.txt  [changed]\n"));
+        assert!(text.contains("✗ a/b.txt  [missing]\n"));
+    }
+
+    #[test]
+    fn verify_checks_only_what_is_listed() {
+        let root = Fixture::new("verify-level", &["a/b/c.txt", "d.txt", ".env"]);
+        let manifest = root.with_extension("ndjson");
+        let output = tree_cli(&["-a", "--output-format", "ndjson", root.to_str().unwrap()]);
+        fs::write(&manifest, &output.stdout).unwrap();
+        let verify = |args: &[&str]| {
+            let mut args = args.to_vec();
+            args.extend([
+                "--verify",
+                manifest.to_str().unwrap(),
+                root.to_str().unwrap(),
+            ]);
+            tree_cli(&args)
+        };
+
+        // 没有读取的目录和被过滤掉的条目不算缺失
+        assert!(verify(&["-L", "1"]).status.success());
+        assert!(verify(&["-E", "a"]).status.success());
+        assert!(verify(&[]).status.success());
+
+        fs::remove_file(root.join("d.txt")).unwrap();
+        let output = verify(&["-L", "1"]);
+        assert_eq!(Some(3), output.status.code());
+        assert!(stdout(&output).contains("✗ d.txt  [missing]\n"));
+        assert!(!stdout(&output).contains("c.txt"));
+    }
+
+    #[test]
+    fn patterns_use_glob_syntax() {
+        let root = Fixture::new(
+            "patterns",
+            &[
+                "src/main.rs",
+                "src/lib.rs",
+                "docs/a.md",
+                "Cargo.toml",
+                "build/out.rs",
+            ],
+        );
+        let root = root.to_str().unwrap();
+
+        let text = stdout(&tree_cli(&["-P", "*.{rs,toml}", root]));
+        assert!(text.contains("main.rs") && text.contains("Cargo.toml"));
+        assert!(!text.contains("a.md"));
+
+        let text = stdout(&tree_cli(&["-P", "!*.rs", root]));
+        assert!(text.contains("a.md") && text.contains("Cargo.toml"));
+        assert!(!text.contains("main.rs"));
+
+        let text = stdout(&tree_cli(&["-P", "src/**", root]));
+        assert!(text.contains("main.rs") && text.contains("lib.rs"));
+        assert!(!text.contains("out.rs") && !text.contains("Cargo.toml"));
+    }
+
+    #[test]
+    fn exclude_prunes_directories_and_wins_over_include() {
+        let root = Fixture::new(
+            "exclude",
+            &["src/main.rs", "build/out.rs", "build/log.txt", "build.rs"],
+        );
+        let root = root.to_str().unwrap();
+
+        let output = tree_cli(&["-E", "build", "-P", "*.rs", root]);
+        let text = stdout(&output);
+        assert!(text.contains("main.rs") && text.contains("build.rs"));
+        assert!(!text.contains("out.rs") && !text.contains("── build\n"));
+        assert!(text.ends_with("\n1 directories, 2 files\n"));
+
+        let output = tree_cli(&["-E", "build", "-E", "!build/out.rs", root]);
+        let text = stdout(&output);
+        assert!(text.contains("── build\n") && text.contains("out.rs"));
+        assert!(!text.contains("log.txt"));
+    }
+
+    #[test]
+    fn slashes_anchor_patterns_and_match_only_directories() {
+        let root = Fixture::new(
+            "exclude-slashes",
+            &["build/out.o", "src/build/gen.rs", "docs/build"],
+        );
+        let root = root.to_str().unwrap();
+
+        let text = stdout(&tree_cli(&["-E", "build/", root]));
+        assert!(!text.contains("out.o") && !text.contains("gen.rs"));
+        assert!(text.contains("── docs\n│   └── build\n"));
+
+        let text = stdout(&tree_cli(&["-E", "/build/", root]));
+        assert!(!text.contains("out.o"));
+        assert!(text.contains("gen.rs") && text.contains("docs\n│   └── build\n"));
+    }
+
+    #[test]
+    fn exclude_rules_agree_across_counts_streams_and_saved_scans() {
+        let root = Fixture::new(
+            "exclude-modes",
+            &[
+                "src/main.rs",
+                "build/keep/a.txt",
+                "build/tmp/b.o",
+                "build/log.txt",
+            ],
+        );
+        let scan = root.with_extension("json");
+        let path = root.to_str().unwrap();
+        let rules = ["-E", "build", "-E", "!build/keep"];
+
+        let listed = stdout(&tree_cli(&[&rules[..], &[path]].concat()));
+        assert!(listed.contains("── build\n") && listed.contains("a.txt"));
+        assert!(!listed.contains("tmp") && !listed.contains("log.txt"));
+        let streamed = tree_cli(&[&rules[..], &["--max-memory", "1", path]].concat());
+        assert_eq!(listed, stdout(&streamed));
+
+        let text = stdout(&tree_cli(
+            &[&rules[..], &["-L", "1", "--count-below", path]].concat(),
+        ));
+        assert!(text.contains("build  … (1 files, 1 dirs)\n"));
+
+        let output = tree_cli(&["--output-format", "json", path]);
+        fs::write(&scan, &output.stdout).unwrap();
+        let replayed = tree_cli(&[&["--from-json", scan.to_str().unwrap()], &rules[..]].concat());
+        assert_eq!(listed, stdout(&replayed));
+        fs::remove_file(&scan).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follow_stops_at_symlink_loops() {
+        let root = Fixture::new("follow-loop", &["a/b/c.txt", "a/b/up -> .."]);
+        let output = tree_cli(&["-l", root.to_str().unwrap()]);
+        let text = stdout(&output);
+
+        assert!(output.status.success());
+        assert!(text.contains("up  [recursive, not followed]\n"));
+        assert_eq!(1, text.matches("c.txt").count());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_counted_separately() {
+        let root = Fixture::new(
+            "symlink-count",
+            &["a/b.txt", "link -> a/b.txt", "dir-link -> a"],
+        );
+        let text = stdout(&tree_cli(&[root.to_str().unwrap()]));
+        assert!(text.ends_with("\n1 directories, 1 files, 2 symlinks\n"));
+
+        // 与 GNU tree 一样，-l 时指向目录的链接算作目录
+        let text = stdout(&tree_cli(&["-l", root.to_str().unwrap()]));
+        assert!(text.ends_with("\n2 directories, 1 files, 1 symlinks\n"));
+    }
+
+    #[test]
+    fn parquet_export_has_one_row_per_entry() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::RowAccessor;
+
+        let root = Fixture::new("parquet", &["a/b.txt", "c.txt"]);
+        let file = root.with_extension("parquet");
+        let output = tree_cli(&["--parquet", file.to_str().unwrap(), root.to_str().unwrap()]);
+        assert!(output.status.success());
+
+        let reader = SerializedFileReader::new(fs::File::open(&file).unwrap()).unwrap();
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(4, rows.len());
+        let names: Vec<String> = rows
+            .iter()
+            .map(|row| row.get_string(0).unwrap().clone())
+            .collect();
+        assert!(names.contains(&root.join("a/b.txt").to_string_lossy().into_owned()));
+        // 第二列 parent 在根目录一行为空
+        assert!(rows[0].get_string(1).is_err());
+        assert_eq!("file", rows[3].get_string(2).unwrap());
+    }
+
+    #[test]
+    fn compare_prints_changes_between_scans() {
+        let root = Fixture::new("compare", &["a/b.txt", "c.txt"]);
+        let scan = |name: &str| {
+            let file = root.with_extension(name);
+            let output = tree_cli(&["--output-format", "ndjson", root.to_str().unwrap()]);
+            fs::write(&file, &output.stdout).unwrap();
+            file
+        };
+        let old = scan("old.ndjson");
+        fs::remove_file(root.join("c.txt")).unwrap();
+        fs::write(root.join("a/new.txt"), "new").unwrap();
+        let new = scan("new.ndjson");
+
+        let output = tree_cli(&["compare", old.to_str().unwrap(), new.to_str().unwrap()]);
+        let text = stdout(&output);
+
+        assert!(output.status.success());
+        assert!(text.contains("+ new.txt\n"));
+        assert!(text.contains("- c.txt\n"));
+        assert!(!text.contains("b.txt"));
+        // 目录 a 的修改时间是否变化取决于两次扫描是否在同一秒内
+        assert!(text.contains("\n1 added, 1 removed, "));
+    }
+
+    #[test]
+    fn diff_compares_a_directory_with_its_archive() {
+        let root = Fixture::new("diff-archive", &["a/b.txt = same", "c.txt", ".hidden"]);
+        let archive = root.with_extension("tar.gz");
+        let encoder = flate2::write::GzEncoder::new(
+            fs::File::create(&archive).unwrap(),
+            flate2::Compression::fast(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        builder.append_dir_all("backup", &*root).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        let diff = |args: &[&str]| {
+            let mut args = args.to_vec();
+            args.extend([root.to_str().unwrap(), archive.to_str().unwrap()]);
+            tree_cli(&args)
+        };
+
+        let output = diff(&["diff"]);
+        assert!(output.status.success());
+        assert!(stdout(&output).ends_with("\n\n0 added, 0 removed, 0 modified\n"));
+
+        // 大小不变的修改只有比较摘要时才能发现
+        fs::write(root.join("a/b.txt"), "SAME").unwrap();
+        fs::remove_file(root.join(".hidden")).unwrap();
+        fs::write(root.join("new.txt"), "new").unwrap();
+        let output = diff(&["diff"]);
+        let text = stdout(&output);
+        assert_eq!(Some(3), output.status.code());
+        assert!(text.contains("├── - .hidden\n└── + new.txt\n"));
+        assert!(!text.contains("b.txt"));
+
+        let output = diff(&["diff", "--hash"]);
+        assert!(stdout(&output).contains("├── a\n│   └── ~ b.txt\n"));
+        assert!(stdout(&output).ends_with("\n1 added, 1 removed, 1 modified\n"));
+
+        fs::write(&archive, "not an archive").unwrap();
+        let output = diff(&["diff"]);
+        assert_eq!(Some(2), output.status.code());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("could not read archive"));
+    }
+
+    #[test]
+    fn dircolors_database_colors_by_type_and_suffix() {
+        let root = Fixture::new("dircolors", &["src/", "query.SQL", "notes.txt"]);
+        let database = root.with_extension("dircolors");
+        fs::write(
+            &database,
+            "# comment\nTERM xterm*\nDIR 01;34 # dirs\n.sql 00;36\n",
+        )
+        .unwrap();
+
+        let output = tree_cli(&[
+            "-C",
+            "--dircolors",
+            database.to_str().unwrap(),
+            root.to_str().unwrap(),
+        ]);
+        let text = stdout(&output);
+
+        assert!(output.status.success());
+        assert!(text.contains("\x1b[01;34msrc"));
+        assert!(text.contains("\x1b[00;36mquery.SQL"));
+        assert!(text.contains("── notes.txt\n"));
+    }
+
+    #[test]
+    fn generate_is_deterministic() {
+        let root = Fixture::new("generate", &[]);
+        let listing = |name: &str| {
+            let dir = root.join(name);
+            let args = ["generate", "--depth", "3", "--files", "20", "--seed", "7"];
+            let output = tree_cli(&[&args[..], &[dir.to_str().unwrap()]].concat());
+            let created = format!(" 20 files in {}\n", dir.display());
+            assert!(stdout(&output).ends_with(&created));
+            stdout(&tree_cli(&["-a", "-s", dir.to_str().unwrap()]))
+        };
+        let first = listing("a");
+        let second = listing("b");
+
+        // 第一行是根目录的路径
+        assert_eq!(
+            first.split_once('\n').unwrap().1,
+            second.split_once('\n').unwrap().1
+        );
+        let output = tree_cli(&["generate", root.join("a").to_str().unwrap()]);
+        assert_eq!(Some(1), output.status.code());
+    }
+
+    #[test]
+    fn mkdir_recreates_a_listed_tree() {
+        let root = Fixture::new(
+            "mkdir",
+            &["src/lib/mod.rs", "src/main.rs", "docs/guide.md", "empty/"],
+        );
+        let listing = root.with_extension("txt");
+        fs::write(&listing, stdout(&tree_cli(&[root.to_str().unwrap()]))).unwrap();
+        let copy = root.with_extension("copy");
+        let _ = fs::remove_dir_all(&copy);
+
+        let output = tree_cli(&[
+            "mkdir",
+            "--from",
+            listing.to_str().unwrap(),
+            copy.to_str().unwrap(),
+        ]);
+        assert!(output.status.success());
+        assert!(copy.join("src/lib/mod.rs").is_file());
+        assert!(copy.join("docs").is_dir());
+        // 没有内容的目录在 tree 的输出中和文件无法区分
+        assert!(copy.join("empty").is_file());
+
+        let sketch = root.with_extension("sketch");
+        fs::write(
+            &sketch,
+            "- app/    # the app\n  - assets/\n  - main.rs\nREADME.md\n",
+        )
+        .unwrap();
+        let output = tree_cli(&[
+            "mkdir",
+            "--from",
+            sketch.to_str().unwrap(),
+            copy.to_str().unwrap(),
+        ]);
+        assert!(stdout(&output).starts_with("created 2 directories, 2 files in "));
+        assert!(copy.join("app/assets").is_dir() && copy.join("app/main.rs").is_file());
+    }
+
+    #[test]
+    fn columns_follow_the_indent_width() {
+        let names: Vec<String> = (1..=6).map(|i| format!("a/b/f{}", i)).collect();
+        let mut spec: Vec<&str> = names.iter().map(String::as_str).collect();
+        spec.push("a/c.txt");
+        let root = Fixture::new("columns-indent", &spec);
+        let output = Command::new(env!("CARGO_BIN_EXE_tree-cli"))
+            .args(["-N", "--compact", "--columns", root.to_str().unwrap()])
+            .env("COLUMNS", "20")
+            .output()
+            .unwrap();
+
+        // 续行与第一行的列对齐，上级的竖线保留
+        let text = stdout(&output);
+        assert!(text.contains("\n  ├ b\n  │ └ f1  f3  f5\n  │   f2  f4  f6\n  └ c.txt\n"));
+    }
+
+    #[test]
+    fn grouped_files_show_their_total_size_only() {
+        let small = format!("a.png = {}", "x".repeat(1000));
+        let large = format!("b.png = {}", "x".repeat(5000));
+        let root = Fixture::new("group-size", &[&small, &large]);
+        let path = root.to_str().unwrap();
+
+        let text = stdout(&tree_cli(&["-s", "--group-by-ext", path]));
+        assert!(text.contains("[       6000]  *.png  (2)\n"));
+        let text = stdout(&tree_cli(&["-s", "--sort", "size", "--group-by-ext", path]));
+        assert!(text.contains("[       6000]  *.png  (2)\n"));
+
+        // 时间等属于单个文件的信息留空
+        let text = stdout(&tree_cli(&["-D", "--group-by-ext", path]));
+        assert!(text.contains("[]  *.png  (2)\n"));
+    }
+
+    #[test]
+    fn max_prefix_depth_counts_the_collapsed_levels() {
+        let root = Fixture::new("prefix-depth", &["a/b/c/z.txt", "a/b/y.txt"]);
+        let text = stdout(&tree_cli(&[
+            "--max-prefix-depth",
+            "1",
+            root.to_str().unwrap(),
+        ]));
+
+        assert!(text.contains("\n└── a\n…1  └── b\n…2  ├── c\n…3  └── z.txt\n…2  └── y.txt\n"));
+    }
+
+    #[test]
+    fn project_config_turns_on_emoji() {
+        let root = Fixture::new("project-emoji", &[".tree-cli.toml = emoji = true", "a.txt"]);
+        let path = root.to_str().unwrap();
+
+        assert!(stdout(&tree_cli(&[path])).contains("── 📄 a.txt\n"));
+        assert!(stdout(&tree_cli(&["--no-config", path])).contains("── a.txt\n"));
+    }
+
+    #[test]
+    fn mark_empty_marks_only_empty_directories() {
+        let root = Fixture::new("mark-empty", &["empty/", "full/a.txt"]);
+        let text = stdout(&tree_cli(&["--mark-empty", root.to_str().unwrap()]));
+
+        assert!(text.contains("── empty  (empty)\n"));
+        assert!(text.contains("── full\n"));
+    }
+
+    #[test]
+    fn bytes_alone_shows_sizes() {
+        let large = format!("a.bin = {}", "x".repeat(12345));
+        let root = Fixture::new("bytes", &[&large]);
+        let path = root.to_str().unwrap();
+
+        let text = stdout(&tree_cli(&["--bytes", path]));
+        assert!(text.contains("[      12345]  a.bin\n"));
+        assert_eq!(text, stdout(&tree_cli(&["-s", "--bytes", path])));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exports_name_the_type_of_special_files() {
+        let root = Fixture::new("special-kinds", &["a.txt"]);
+        let status = Command::new("mkfifo").arg(root.join("pipe")).status();
+        assert!(status.unwrap().success());
+        let path = root.to_str().unwrap();
+
+        let csv = stdout(&tree_cli(&["--output-format", "csv", path]));
+        assert!(csv.contains("/pipe,pipe,fifo,0,1,"));
+        assert!(csv.contains("/a.txt,a.txt,file,"));
+        let manifest = root.with_extension("ndjson");
+        let output = tree_cli(&["--output-format", "ndjson", path]);
+        fs::write(&manifest, &output.stdout).unwrap();
+        let output = tree_cli(&["--verify", manifest.to_str().unwrap(), path]);
+        assert!(output.status.success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn mkdir_strips_the_markers_of_its_own_output() {
+        let root = Fixture::new("mkdir-markers", &["img/a.png", "img/b.png", "notes.txt"]);
+        let status = Command::new("mkfifo").arg(root.join("pipe")).status();
+        assert!(status.unwrap().success());
+        let listing = root.with_extension("txt");
+        let output = tree_cli(&["--group-by-ext", root.to_str().unwrap()]);
+        let text = stdout(&output);
+        assert!(text.contains("*.png  (2)\n") && text.contains("pipe  [fifo]\n"));
+        fs::write(&listing, text).unwrap();
+        let copy = root.with_extension("copy");
+        let _ = fs::remove_dir_all(&copy);
+
+        let output = tree_cli(&[
+            "mkdir",
+            "--from",
+            listing.to_str().unwrap(),
+            copy.to_str().unwrap(),
+        ]);
+        assert!(output.status.success());
+        assert!(copy.join("pipe").is_file() && copy.join("img/*.png").is_file());
+        let mut names: Vec<_> = fs::read_dir(copy.join("img"))
+            .unwrap()
+            .chain(fs::read_dir(&copy).unwrap())
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(["*.png", "img", "notes.txt", "pipe"], &names[..]);
+        fs::remove_dir_all(&copy).unwrap();
+        fs::remove_file(&listing).unwrap();
+    }
+
+    #[test]
+    fn max_name_len_keeps_shortened_names_distinct() {
+        let root = Fixture::new(
+            "max-name-len",
+            &[
+                "3f2a9c1e-77b4-4c1f-9a0e-5d8f2b6c4e1a.json",
+                "3f2a9c1e-77b4-4c1f-9a0e-5d8f2b6c4e1b.json",
+                "short.txt",
+            ],
+        );
+        let text = stdout(&tree_cli(&["--max-name-len", "20", root.to_str().unwrap()]));
+        let names: Vec<&str> = text
+            .lines()
+            .filter_map(|line| line.split_once("── ").map(|(_, name)| name))
+            .collect();
+
+        assert_eq!(3, names.len());
+        assert!(names[0].starts_with("3f2a9c1e…") && names[0].ends_with(".json"));
+        assert!(names[1].starts_with("3f2a9c1e…") && names[1] != names[0]);
+        assert!(names.iter().all(|name| name.chars().count() <= 20));
+        assert_eq!("short.txt", names[2]);
+    }
+
+    #[test]
+    fn from_json_renders_a_saved_scan() {
+        let root = Fixture::new("from-json", &["b/c.txt", "a.txt", ".hidden"]);
+        let scan = root.with_extension("json");
+        let output = tree_cli(&["--output-format", "json", "-a", root.to_str().unwrap()]);
+        fs::write(&scan, &output.stdout).unwrap();
+        let listed = stdout(&tree_cli(&["--dirsfirst", root.to_str().unwrap()]));
+        let report = ["--dirsfirst", "--report-types", "--inode-usage"];
+        let reported = stdout(&tree_cli(
+            &[&report[..], &[root.to_str().unwrap()]].concat(),
+        ));
+        fs::remove_dir_all(&root).unwrap();
+
+        let output = tree_cli(&["--from-json", scan.to_str().unwrap(), "--dirsfirst"]);
+        assert!(output.status.success());
+        assert_eq!(listed, stdout(&output));
+        let output = tree_cli(&[&report[..], &["--from-json", scan.to_str().unwrap()]].concat());
+        // `--inode-usage` 时磁盘上的列表每行还有 inode 数，只比较结尾的统计
+        let report_lines = |text: &str| text.lines().rev().take(2).map(String::from).collect();
+        let replayed: Vec<String> = report_lines(&stdout(&output));
+        assert_eq!(report_lines(&reported), replayed);
+
+        let text = stdout(&tree_cli(&[
+            "--from-json",
+            scan.to_str().unwrap(),
+            "-a",
+            "-L",
+            "1",
+        ]));
+        assert!(text.contains("├── .hidden\n"));
+        assert!(text.ends_with("\n1 directories, 2 files\n"));
+    }
+
+    #[test]
+    fn pins_list_each_directory_with_its_options() {
+        let first = Fixture::new("pin-first", &["a/b/c.txt", "d.txt"]);
+        let second = Fixture::new("pin-second", &["notes.md", "todo.txt"]);
+        let state = first.with_extension("toml");
+        let pins = |args: &[&str]| {
+            Command::new(env!("CARGO_BIN_EXE_tree-cli"))
+                .env("TREE_CLI_PINS", &state)
+                .args(["-N", "--lang", "en"])
+                .args(args)
+                .output()
+                .unwrap()
+        };
+
+        let output = pins(&["pin", first.to_str().unwrap(), "--", "-L", "1"]);
+        assert!(output.status.success());
+        assert!(pins(&["pin", second.to_str().unwrap(), "--", "-P", "*.md"])
+            .status
+            .success());
+        let text = stdout(&pins(&["pins"]));
+        assert!(text.contains("── a\n") && !text.contains("c.txt"));
+        assert!(text.contains("notes.md") && !text.contains("todo.txt"));
+
+        // 命令行上的选项覆盖收藏时保存的同一个选项
+        let output = pins(&["-L", "3", "pins"]);
+        assert!(output.status.success());
+        assert!(stdout(&output).contains("c.txt"));
+
+        assert!(pins(&["pin", "--remove", second.to_str().unwrap()])
+            .status
+            .success());
+        let text = stdout(&pins(&["pins", "--list"]));
+        assert_eq!(format!("{} -L 1\n", first.display()), text);
+
+        let output = pins(&["pin", first.to_str().unwrap(), "--", "--no-such-option"]);
+        assert_eq!(Some(1), output.status.code());
+        fs::remove_file(&state).unwrap();
+    }
+
+    #[test]
+    fn network_profile_lists_the_same_tree() {
+        let root = Fixture::new("profile", &["a/b.txt", "c.txt"]);
+        let path = root.to_str().unwrap();
+        let local = tree_cli(&["--profile", "local", path]);
+        let network = tree_cli(&["--profile", "network", path]);
+
+        assert!(network.status.success());
+        assert_eq!(stdout(&local), stdout(&network));
+    }
+
+    #[test]
+    fn stats_run_on_the_requested_threads() {
+        let names: Vec<String> = (0..100).map(|i| format!("f{:03}", i)).collect();
+        let spec: Vec<&str> = names.iter().map(String::as_str).collect();
+        let root = Fixture::new("threads", &spec);
+        let path = root.to_str().unwrap();
+        // 列表和 `--timing` 报告的线程数，输出相同也要确认确实用了给定的线程数
+        let scan = |args: &[&str]| {
+            let output =
+                tree_cli(&[&["--output-format", "csv", "--timing"], args, &[path]].concat());
+            assert!(output.status.success());
+            let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+            (stdout(&output), stderr)
+        };
+        // 网络配置总是带着超时，默认每个 CPU 一个线程，最多 8 个
+        let network = std::thread::available_parallelism().map_or(1, |n| n.get().min(8));
+        let cases: [(&[&str], usize); 6] = [
+            (&["--threads", "1"], 1),
+            (&["--threads", "4"], 4),
+            (&["--threads", "1", "--timeout", "5"], 1),
+            (&["--threads", "4", "--timeout", "5"], 4),
+            (&["--profile", "network"], network),
+            (&["--profile", "network", "--threads", "3"], 3),
+        ];
+
+        let (listing, _) = scan(&["--threads", "1"]);
+        for (args, threads) in cases {
+            let (text, stderr) = scan(args);
+            assert_eq!(listing, text, "{:?}", args);
+            let report = format!("100 entries stat'ed on {} threads", threads);
+            assert!(stderr.contains(&report), "{:?}: {}", args, stderr);
+        }
+    }
+
+    #[test]
+    fn mtime_sort_uses_the_chosen_timestamp() {
+        use std::fs::FileTimes;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let root = Fixture::new("sort-time", &["a.txt", "b.txt"]);
+        let set = |name: &str, modified: u64, accessed: u64| {
+            let times = FileTimes::new()
+                .set_modified(UNIX_EPOCH + Duration::from_secs(modified))
+                .set_accessed(UNIX_EPOCH + Duration::from_secs(accessed));
+            let file = fs::File::options().write(true).open(root.join(name));
+            file.unwrap().set_times(times).unwrap();
+        };
+        set("a.txt", 1_000_000_000, 1_600_000_000);
+        set("b.txt", 1_300_000_000, 1_000_000_000);
+        let path = root.to_str().unwrap();
+
+        let text = stdout(&tree_cli(&["--sort", "mtime", path]));
+        assert!(text.contains("\n├── b.txt\n└── a.txt\n"));
+        let text = stdout(&tree_cli(&["--sort", "mtime", "--time", "atime", path]));
+        assert!(text.contains("\n├── a.txt\n└── b.txt\n"));
+    }
+
+    #[test]
+    fn timing_goes_to_stderr() {
+        let root = Fixture::new("timing", &["a/b.txt", "c.txt"]);
+        let path = root.to_str().unwrap();
+        let plain = tree_cli(&[path]);
+        let timed = tree_cli(&["--timing", path]);
+
+        assert_eq!(stdout(&plain), stdout(&timed));
+        assert!(String::from_utf8_lossy(&timed.stderr).contains("entries stat'ed"));
+        let quiet = tree_cli(&["--timing", "--quiet", path]);
+        assert!(quiet.stderr.is_empty());
+    }
+
+    #[test]
+    fn max_memory_streams_large_trees() {
+        let root = Fixture::new("max-memory", &[]);
+        let dir = root.join("tree");
+        let args = ["generate", "--depth", "4", "--files", "5000", "--seed", "1"];
+        tree_cli(&[&args[..], &[dir.to_str().unwrap()]].concat());
+        let path = dir.to_str().unwrap();
+        let listed = tree_cli(&["-a", "--dirsfirst", path]);
+        let streamed = tree_cli(&["-a", "--dirsfirst", "--max-memory", "1", path]);
+
+        assert!(streamed.status.success());
+        assert_eq!(stdout(&listed), stdout(&streamed));
+        assert!(String::from_utf8_lossy(&streamed.stderr).contains("does not fit in 1 MB"));
+        let quiet = tree_cli(&["-a", "--max-memory", "1", "--quiet", path]);
+        assert!(quiet.status.success() && quiet.stderr.is_empty());
+        let small = tree_cli(&["--max-memory", "64", path]);
+        assert!(small.stderr.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn json_errors_cover_streamed_trees() {
+        let root = Fixture::new("errors-streamed", &[]);
+        let dir = root.join("tree");
+        let args = ["generate", "--depth", "4", "--files", "5000", "--seed", "1"];
+        tree_cli(&[&args[..], &[dir.to_str().unwrap()]].concat());
+        std::os::unix::fs::symlink("nowhere", dir.join("zz-dangling")).unwrap();
+        let path = dir.to_str().unwrap();
+        let errors = |args: &[&str]| {
+            let output = tree_cli(&[&["-l", "--errors", "json"], args, &[path]].concat());
+            String::from_utf8_lossy(&output.stderr)
+                .lines()
+                .filter(|line| line.starts_with('{'))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        let listed = errors(&[]);
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].contains(r#"zz-dangling","operation":"link_target""#));
+        assert_eq!(listed, errors(&["--max-memory", "1"]));
+    }
+
+    #[test]
+    fn bfs_traversal_lists_levels_in_order() {
+        let root = Fixture::new("traversal", &["a/deep/x.txt", "a/y.txt", "b.txt"]);
+        let path = root.to_str().unwrap();
+        let names = |traversal: &str| -> Vec<String> {
+            let args = ["--output-format", "csv", "--traversal", traversal, path];
+            let text = stdout(&tree_cli(&args));
+            let names = text
+                .lines()
+                .skip(1)
+                .map(|line| line.split(',').nth(1).unwrap());
+            names.map(str::to_string).collect()
+        };
+
+        assert_eq!(names("dfs")[1..], ["a", "deep", "x.txt", "y.txt", "b.txt"]);
+        assert_eq!(names("bfs")[1..], ["a", "b.txt", "deep", "y.txt", "x.txt"]);
+    }
+
+    #[test]
+    fn no_sort_lists_the_same_entries() {
+        let root = Fixture::new("no-sort", &["c.txt", "a/b.txt", "b.txt", "a/a.txt"]);
+        let path = root.to_str().unwrap();
+        let rows = |args: &[&str]| -> Vec<String> {
+            let args = [&["--output-format", "csv"], args, &[path]].concat();
+            let mut rows: Vec<String> =
+                stdout(&tree_cli(&args)).lines().map(String::from).collect();
+            rows.sort();
+            rows
+        };
+
+        assert_eq!(rows(&[]), rows(&["-U"]));
+        assert_eq!(
+            tree_cli(&["-U", "--dirsfirst", path]).status.code(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn fail_if_empty_exits_when_nothing_matches() {
+        let root = Fixture::new("fail-if-empty", &["vendor/a/COPYING", "vendor/b.license"]);
+        let path = root.to_str().unwrap();
+
+        let found = tree_cli(&["-P", "*.license", "--fail-if-empty", path]);
+        assert!(found.status.success());
+        let missing = tree_cli(&["-P", "*.txt", "--fail-if-empty", path]);
+        assert_eq!(Some(4), missing.status.code());
+        assert!(stdout(&missing).ends_with("\n0 directories, 0 files\n"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn show_fs_marks_only_mount_points() {
+        let output = stdout(&tree_cli(&["--show-fs", "-L", "2", "/"]));
+        if !output.contains("── proc  [proc]") {
+            return;
+        }
+        // /proc 下的进程目录和 /proc 在同一个文件系统上
+        let below_proc = output.lines().filter(|line| {
+            let name = line.trim_start_matches(|c: char| !c.is_ascii_alphanumeric());
+            name.ends_with("  [proc]") && name.starts_with(|c: char| c.is_ascii_digit())
+        });
+        assert_eq!(below_proc.count(), 0);
+    }
 }